@@ -0,0 +1,134 @@
+//! Codec identification.
+
+/// The codec of a logical bitstream, as identified by the packet of its BOS page.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Codec {
+    /// Vorbis audio.
+    Vorbis,
+    /// Opus audio.
+    Opus,
+    /// FLAC audio.
+    Flac,
+    /// Theora video.
+    Theora,
+    /// Speex audio.
+    Speex,
+    /// Skeleton metadata.
+    Skeleton,
+    /// Kate overlays and subtitles.
+    Kate,
+    /// Daala video.
+    Daala,
+    /// VP8 video.
+    Vp8,
+    /// Uncompressed PCM audio.
+    OggPcm,
+    /// The codec could not be identified.
+    Unknown,
+}
+
+/// The magic bytes at the start of the BOS packet of each codec.
+const MAGIC_BYTES: [(&[u8], Codec); 10] = [
+    (b"\x01vorbis", Codec::Vorbis),
+    (b"OpusHead", Codec::Opus),
+    (b"\x7FFLAC", Codec::Flac),
+    (b"\x80theora", Codec::Theora),
+    (b"Speex   ", Codec::Speex),
+    (b"fishead\0", Codec::Skeleton),
+    (b"\x80kate\0\0\0", Codec::Kate),
+    (b"\x80daala", Codec::Daala),
+    (b"OVP80", Codec::Vp8),
+    (b"PCM     ", Codec::OggPcm),
+];
+
+impl Codec {
+    /// Identifies the codec by the magic bytes of the packet of the BOS page.
+    pub fn detect(bos_packet: &[u8]) -> Codec {
+        MAGIC_BYTES
+            .iter()
+            .find(|(magic, _)| bos_packet.starts_with(magic))
+            .map_or(Codec::Unknown, |(_, codec)| *codec)
+    }
+
+    /// Returns the sample rate of an audio codec, which is the rate of its granule positions.
+    ///
+    /// Returns `None` for other codecs or if the BOS packet is too short.
+    #[cfg(feature = "reader")]
+    pub(crate) fn sample_rate(&self, bos_packet: &[u8]) -> Option<u32> {
+        let rate = match self {
+            Codec::Vorbis => u32::from_le_bytes(read_array(bos_packet, 12)?),
+            Codec::Opus => 48_000,
+            Codec::Flac => {
+                // The 20 bit sample rate of the STREAMINFO block.
+                let bytes: [u8; 3] = read_array(bos_packet, 27)?;
+                u32::from(bytes[0]) << 12 | u32::from(bytes[1]) << 4 | u32::from(bytes[2]) >> 4
+            }
+            Codec::Speex => u32::from_le_bytes(read_array(bos_packet, 36)?),
+            Codec::OggPcm => u32::from_be_bytes(read_array(bos_packet, 16)?),
+            _ => return None,
+        };
+
+        if rate == 0 {
+            None
+        } else {
+            Some(rate)
+        }
+    }
+}
+
+/// Reads a fixed size array at the given offset.
+#[cfg(feature = "reader")]
+fn read_array<const N: usize>(source: &[u8], offset: usize) -> Option<[u8; N]> {
+    let mut buffer = [0_u8; N];
+    buffer.copy_from_slice(source.get(offset..offset + N)?);
+    Some(buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::panic)]
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(Codec::detect(b"\x01vorbis\0\0\0\0\x02"), Codec::Vorbis);
+        assert_eq!(Codec::detect(b"OpusHead\x01\x02"), Codec::Opus);
+        assert_eq!(Codec::detect(b"\x7FFLAC\x01\0"), Codec::Flac);
+        assert_eq!(Codec::detect(b"\x80theora\x03\x02"), Codec::Theora);
+        assert_eq!(Codec::detect(b"Speex   1.2"), Codec::Speex);
+        assert_eq!(Codec::detect(b"fishead\0\x04\0"), Codec::Skeleton);
+        assert_eq!(Codec::detect(b"\x80kate\0\0\0\0"), Codec::Kate);
+        assert_eq!(Codec::detect(b"\x80daala\0"), Codec::Daala);
+        assert_eq!(Codec::detect(b"OVP80\x01\x01"), Codec::Vp8);
+        assert_eq!(Codec::detect(b"PCM     \0\0"), Codec::OggPcm);
+    }
+
+    #[test]
+    fn test_detect_unknown() {
+        assert_eq!(Codec::detect(b""), Codec::Unknown);
+        assert_eq!(Codec::detect(b"Opus"), Codec::Unknown);
+        assert_eq!(Codec::detect(b"\x03vorbis"), Codec::Unknown);
+    }
+
+    #[test]
+    #[cfg(feature = "reader")]
+    fn test_sample_rate() {
+        let vorbis = b"\x01vorbis\0\0\0\0\x02\x44\xAC\0\0";
+        assert_eq!(Codec::Vorbis.sample_rate(vorbis), Some(44_100));
+        assert_eq!(Codec::Opus.sample_rate(b"OpusHead"), Some(48_000));
+
+        let mut flac = b"\x7FFLAC\x01\0\0\x01fLaC\0\0\0\x22".to_vec();
+        flac.extend_from_slice(&[0x10, 0x00, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+        flac.extend_from_slice(&[0x0B, 0xB8, 0x02, 0xF0]);
+        assert_eq!(Codec::Flac.sample_rate(&flac), Some(48_000));
+
+        let mut pcm = b"PCM     \0\0\0\0\0\0\0\0".to_vec();
+        pcm.extend_from_slice(&22_050_u32.to_be_bytes());
+        assert_eq!(Codec::OggPcm.sample_rate(&pcm), Some(22_050));
+
+        assert_eq!(Codec::Vorbis.sample_rate(b"\x01vorbis"), None);
+        assert_eq!(Codec::Theora.sample_rate(b"\x80theora"), None);
+    }
+}
@@ -0,0 +1,79 @@
+//! Parsing of the comment lists used by the comment headers of Vorbis and Opus.
+
+use std::convert::{TryFrom, TryInto};
+
+/// The user comments as tag and value pairs.
+pub(crate) type Comments = Vec<(String, String)>;
+
+/// Parses the vendor string and the user comments of a comment list and returns the data
+/// after the list.
+///
+/// User comments are split into their tag and value. Comments without a `=` are ignored.
+pub(crate) fn parse_comment_list(data: &[u8]) -> Option<(String, Comments, &[u8])> {
+    let mut data = data;
+
+    let vendor = read_string(&mut data)?;
+    let count = read_u32(&mut data)?;
+
+    // Each comment needs at least its length.
+    if usize::try_from(count).ok()? > data.len() / 4 {
+        return None;
+    }
+
+    let mut comments = Vec::with_capacity(usize::try_from(count).ok()?);
+    for _ in 0..count {
+        let comment = read_string(&mut data)?;
+        if let Some((tag, value)) = comment.split_once('=') {
+            comments.push((tag.to_owned(), value.to_owned()));
+        }
+    }
+
+    Some((vendor, comments, data))
+}
+
+/// Returns the value of the first comment with the given tag, which is compared case
+/// insensitive.
+pub(crate) fn find_comment<'a>(comments: &'a [(String, String)], tag: &str) -> Option<&'a str> {
+    comments
+        .iter()
+        .find(|(t, _)| t.eq_ignore_ascii_case(tag))
+        .map(|(_, value)| value.as_str())
+}
+
+/// Writes the vendor string and the user comments as a comment list.
+///
+/// Returns `None` if a string is too long.
+pub(crate) fn write_comment_list(
+    target: &mut Vec<u8>,
+    vendor: &str,
+    comments: &[(String, String)],
+) -> Option<()> {
+    write_string(target, vendor.as_bytes())?;
+    target.extend_from_slice(&u32::try_from(comments.len()).ok()?.to_le_bytes());
+    for (tag, value) in comments.iter() {
+        write_string(target, format!("{}={}", tag, value).as_bytes())?;
+    }
+    Some(())
+}
+
+/// Writes a string that is prefixed with its length.
+fn write_string(target: &mut Vec<u8>, string: &[u8]) -> Option<()> {
+    target.extend_from_slice(&u32::try_from(string.len()).ok()?.to_le_bytes());
+    target.extend_from_slice(string);
+    Some(())
+}
+
+/// Reads a little endian `u32`.
+fn read_u32(data: &mut &[u8]) -> Option<u32> {
+    let bytes = data.get(..4)?.try_into().ok()?;
+    *data = &data[4..];
+    Some(u32::from_le_bytes(bytes))
+}
+
+/// Reads an UTF-8 string that is prefixed with its length. Invalid characters are replaced.
+fn read_string(data: &mut &[u8]) -> Option<String> {
+    let length = usize::try_from(read_u32(data)?).ok()?;
+    let string = String::from_utf8_lossy(data.get(..length)?).into_owned();
+    *data = &data[length..];
+    Some(string)
+}
@@ -0,0 +1,307 @@
+//! Mapping of granule positions to time.
+
+use std::convert::TryFrom;
+use std::time::Duration;
+
+pub(crate) const NANOS_PER_SEC: u32 = 1_000_000_000;
+
+/// Maps the granule positions of a logical bitstream to time and back.
+///
+/// The encoding of granule positions is defined by the media mapping of each codec.
+pub trait GranuleMapper {
+    /// Returns the time of the given granule position.
+    ///
+    /// Returns `None` if the granule position has no valid time.
+    fn granule_to_time(&self, granule_position: u64) -> Option<Duration>;
+
+    /// Returns the smallest granule position that has the given time or a later one.
+    fn time_to_granule(&self, time: Duration) -> u64;
+
+    /// Returns a value that orders granule positions by time, like the frame number of
+    /// split granule positions.
+    ///
+    /// The default implementation returns the granule position itself.
+    fn granule_order(&self, granule_position: u64) -> u64 {
+        granule_position
+    }
+
+    /// Returns the granule position of the keyframe that the given granule position
+    /// depends on.
+    ///
+    /// The default implementation returns `None` for codecs without keyframes.
+    fn keyframe_granule(&self, _granule_position: u64) -> Option<u64> {
+        None
+    }
+}
+
+/// Maps granule positions that count samples at a fixed sample rate.
+///
+/// Used by Vorbis, FLAC, Speex and OggPCM.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SampleRateMapper {
+    sample_rate: u32,
+}
+
+impl SampleRateMapper {
+    /// Creates a new `SampleRateMapper`. The sample rate can't be zero.
+    pub fn new(sample_rate: u32) -> Option<Self> {
+        if sample_rate == 0 {
+            None
+        } else {
+            Some(Self { sample_rate })
+        }
+    }
+
+    /// The sample rate of the logical bitstream.
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+}
+
+impl GranuleMapper for SampleRateMapper {
+    fn granule_to_time(&self, granule_position: u64) -> Option<Duration> {
+        units_to_time(granule_position, 1, self.sample_rate)
+    }
+
+    fn time_to_granule(&self, time: Duration) -> u64 {
+        time_to_units(time, self.sample_rate, 1)
+    }
+}
+
+/// Maps the granule positions of Opus, which count samples at 48 kHz including the
+/// pre-skip.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct OpusMapper {
+    pre_skip: u16,
+}
+
+impl OpusMapper {
+    /// The sample rate of all Opus granule positions.
+    pub const SAMPLE_RATE: u32 = 48_000;
+
+    /// Creates a new `OpusMapper` with the pre-skip of the OpusHead packet.
+    pub fn new(pre_skip: u16) -> Self {
+        Self { pre_skip }
+    }
+
+    /// The samples that are discarded at the start of the logical bitstream.
+    pub fn pre_skip(&self) -> u16 {
+        self.pre_skip
+    }
+}
+
+impl GranuleMapper for OpusMapper {
+    fn granule_to_time(&self, granule_position: u64) -> Option<Duration> {
+        let samples = granule_position.checked_sub(u64::from(self.pre_skip))?;
+        units_to_time(samples, 1, Self::SAMPLE_RATE)
+    }
+
+    fn time_to_granule(&self, time: Duration) -> u64 {
+        time_to_units(time, Self::SAMPLE_RATE, 1).saturating_add(u64::from(self.pre_skip))
+    }
+}
+
+/// Maps the granule positions of Theora, which are split by the granule shift into
+/// the frame number of the last keyframe and the frames since that keyframe.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct TheoraMapper {
+    granule_shift: u8,
+    fps_numerator: u32,
+    fps_denominator: u32,
+}
+
+impl TheoraMapper {
+    /// Creates a new `TheoraMapper`. The frame rate can't be zero and the granule shift
+    /// can't be bigger than 31.
+    pub fn new(granule_shift: u8, fps_numerator: u32, fps_denominator: u32) -> Option<Self> {
+        if granule_shift > 31 || fps_numerator == 0 || fps_denominator == 0 {
+            return None;
+        }
+
+        Some(Self {
+            granule_shift,
+            fps_numerator,
+            fps_denominator,
+        })
+    }
+
+    /// The amount of bits used for the frames since the last keyframe.
+    pub fn granule_shift(&self) -> u8 {
+        self.granule_shift
+    }
+
+    /// Returns the frame number of the last keyframe and the frames since that keyframe.
+    pub fn split_granule(&self, granule_position: u64) -> (u64, u64) {
+        let mask = (1_u64 << self.granule_shift) - 1;
+        (
+            granule_position >> self.granule_shift,
+            granule_position & mask,
+        )
+    }
+
+    /// Returns the frame number of the given granule position.
+    pub fn frame(&self, granule_position: u64) -> u64 {
+        let (keyframe, offset) = self.split_granule(granule_position);
+        keyframe.saturating_add(offset)
+    }
+}
+
+impl GranuleMapper for TheoraMapper {
+    fn granule_to_time(&self, granule_position: u64) -> Option<Duration> {
+        units_to_time(
+            self.frame(granule_position),
+            self.fps_denominator,
+            self.fps_numerator,
+        )
+    }
+
+    /// Returns the granule position of a keyframe at the given time.
+    fn time_to_granule(&self, time: Duration) -> u64 {
+        let frame = time_to_units(time, self.fps_numerator, self.fps_denominator);
+        frame
+            .checked_shl(u32::from(self.granule_shift))
+            .filter(|granule| granule >> self.granule_shift == frame)
+            .unwrap_or(u64::MAX)
+    }
+
+    fn granule_order(&self, granule_position: u64) -> u64 {
+        self.frame(granule_position)
+    }
+
+    fn keyframe_granule(&self, granule_position: u64) -> Option<u64> {
+        Some(granule_position >> self.granule_shift << self.granule_shift)
+    }
+}
+
+/// Returns the granule mapper of the codec identified by the BOS packet.
+#[cfg(feature = "reader")]
+pub(crate) fn granule_mapper(bos_packet: &[u8]) -> Option<Box<dyn GranuleMapper>> {
+    use crate::Codec;
+
+    let codec = Codec::detect(bos_packet);
+    match codec {
+        Codec::Opus => {
+            let head = crate::OpusHead::parse(bos_packet)?;
+            Some(Box::new(OpusMapper::new(head.pre_skip())))
+        }
+        Codec::Theora => {
+            let header = bos_packet.get(22..42)?;
+            let fps_numerator = u32::from_be_bytes([header[0], header[1], header[2], header[3]]);
+            let fps_denominator = u32::from_be_bytes([header[4], header[5], header[6], header[7]]);
+            let granule_shift = (header[18] & 0x03) << 3 | header[19] >> 5;
+            Some(Box::new(TheoraMapper::new(
+                granule_shift,
+                fps_numerator,
+                fps_denominator,
+            )?))
+        }
+        _ => Some(Box::new(SampleRateMapper::new(
+            codec.sample_rate(bos_packet)?,
+        )?)),
+    }
+}
+
+/// Returns the time of `units * numerator / denominator` seconds.
+fn units_to_time(units: u64, numerator: u32, denominator: u32) -> Option<Duration> {
+    fraction_to_time(
+        u128::from(units) * u128::from(numerator),
+        u128::from(denominator),
+    )
+}
+
+/// Returns the time of `numerator / denominator` seconds.
+///
+/// Returns `None` if the denominator is zero or the time doesn't fit into a `Duration`.
+pub(crate) fn fraction_to_time(numerator: u128, denominator: u128) -> Option<Duration> {
+    let nanos = numerator
+        .checked_mul(u128::from(NANOS_PER_SEC))?
+        .checked_div(denominator)?;
+    let secs = u64::try_from(nanos / u128::from(NANOS_PER_SEC)).ok()?;
+    let nanos = u32::try_from(nanos % u128::from(NANOS_PER_SEC)).ok()?;
+    Some(Duration::new(secs, nanos))
+}
+
+/// Returns the amount of units of `denominator / numerator` seconds in the given time,
+/// rounded up.
+fn time_to_units(time: Duration, numerator: u32, denominator: u32) -> u64 {
+    let divisor = u128::from(denominator) * u128::from(NANOS_PER_SEC);
+    let units = (time.as_nanos() * u128::from(numerator) + divisor - 1) / divisor;
+    u64::try_from(units).unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::panic)]
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+
+    #[test]
+    fn test_sample_rate_mapper() {
+        let mapper = SampleRateMapper::new(44_100).unwrap();
+        assert_eq!(
+            mapper.granule_to_time(44_100 * 3 + 22_050),
+            Some(Duration::from_millis(3500))
+        );
+        assert_eq!(mapper.time_to_granule(Duration::from_millis(3500)), 154_350);
+        assert_eq!(mapper.time_to_granule(Duration::from_nanos(1)), 1);
+        assert!(SampleRateMapper::new(0).is_none());
+    }
+
+    #[test]
+    fn test_opus_mapper() {
+        let mapper = OpusMapper::new(312);
+        assert_eq!(mapper.granule_to_time(312), Some(Duration::from_secs(0)));
+        assert_eq!(
+            mapper.granule_to_time(312 + 48_000),
+            Some(Duration::from_secs(1))
+        );
+        assert_eq!(mapper.granule_to_time(100), None);
+        assert_eq!(mapper.time_to_granule(Duration::from_millis(20)), 312 + 960);
+    }
+
+    #[test]
+    fn test_theora_mapper() {
+        let mapper = TheoraMapper::new(6, 30_000, 1001).unwrap();
+        let granule_position = (60 << 6) | 15;
+        assert_eq!(mapper.split_granule(granule_position), (60, 15));
+        assert_eq!(mapper.frame(granule_position), 75);
+        assert_eq!(
+            mapper.granule_to_time(granule_position),
+            Some(Duration::from_nanos(2_502_500_000))
+        );
+        assert_eq!(
+            mapper.time_to_granule(Duration::from_nanos(2_502_500_000)),
+            75 << 6
+        );
+        assert_eq!(mapper.granule_order(granule_position), 75);
+        assert_eq!(mapper.keyframe_granule(granule_position), Some(60 << 6));
+        assert!(TheoraMapper::new(32, 30, 1).is_none());
+    }
+
+    #[test]
+    #[cfg(feature = "reader")]
+    fn test_granule_mapper() {
+        let mut opus_head = b"OpusHead\x01\x02".to_vec();
+        opus_head.extend_from_slice(&[0x38, 0x01, 0x80, 0xBB, 0x00, 0x00, 0x00, 0x00, 0x00]);
+        let mapper = granule_mapper(&opus_head).unwrap();
+        assert_eq!(
+            mapper.granule_to_time(312 + 48_000),
+            Some(Duration::from_secs(1))
+        );
+
+        let mut theora_id = b"\x80theora\x03\x02\x01".to_vec();
+        theora_id.resize(22, 0);
+        theora_id.extend_from_slice(&25_u32.to_be_bytes());
+        theora_id.extend_from_slice(&1_u32.to_be_bytes());
+        theora_id.resize(40, 0);
+        theora_id.extend_from_slice(&[0x01, 0x40]);
+        let mapper = granule_mapper(&theora_id).unwrap();
+        assert_eq!(
+            mapper.granule_to_time(50 << 10),
+            Some(Duration::from_secs(2))
+        );
+
+        assert!(granule_mapper(b"unknown").is_none());
+    }
+}
@@ -48,16 +48,42 @@
 use std::hash::{Hash, Hasher};
 use std::ops::Range;
 
+pub use codec::Codec;
+pub use granule::{GranuleMapper, OpusMapper, SampleRateMapper, TheoraMapper};
+pub use opus::{OpusChannel, OpusHead, OpusTags};
+pub use page::Page;
 #[cfg(feature = "reader")]
 pub use read_error::ReadError;
+#[cfg(all(feature = "reader", feature = "async"))]
+pub use reader::AsyncStreamReader;
 #[cfg(feature = "reader")]
-pub use reader::{FileReader, Packet, ReadStatus, StreamReader};
+pub use reader::{
+    Chain, FileReader, OpusPacket, OpusReader, Packet, PushReader, ReadStatus, SeekIndex,
+    SeekResult, StreamInfo, StreamReader,
+};
+pub use skeleton::{Fisbone, Fishead, Keypoint, SkeletonIndex};
+#[cfg(feature = "tokio")]
+pub use tokio_compat::TokioCompat;
+pub use vorbis::{VorbisComment, VorbisIdentification, VorbisSetup};
 #[cfg(feature = "writer")]
 pub use write_error::WriteError;
+#[cfg(all(feature = "writer", feature = "async"))]
+pub use writer::AsyncStreamWriter;
 #[cfg(feature = "writer")]
-pub use writer::StreamWriter;
+pub use writer::{OpusWriter, SkeletonWriter, StreamWriter, VorbisWriter};
 
+mod codec;
+mod comment;
 pub(crate) mod crc32;
+mod granule;
+mod opus;
+mod page;
+mod skeleton;
+#[cfg(test)]
+mod test_util;
+#[cfg(feature = "tokio")]
+mod tokio_compat;
+mod vorbis;
 
 #[cfg(feature = "reader")]
 mod read_error;
@@ -82,7 +108,6 @@ pub(crate) const SEGMENT_COUNT_INDEX: usize = 26;
 pub(crate) const SEGMENT_TABLE_INDEX: usize = 27;
 pub(crate) const HEADER_RANGE: Range<usize> = Range { start: 0, end: 27 };
 pub(crate) const PAGER_MARKER_RANGE: Range<usize> = Range { start: 0, end: 4 };
-pub(crate) const CONST_HEADER_DATA_RANGE: Range<usize> = Range { start: 4, end: 27 };
 pub(crate) const GRANULE_POSITION_RANGE: Range<usize> = Range { start: 6, end: 14 };
 pub(crate) const BITSTREAM_SERIAL_NUMBER_RANGE: Range<usize> = Range { start: 14, end: 18 };
 pub(crate) const PAGE_SEQUENCE_NUMBER_RANGE: Range<usize> = Range { start: 18, end: 22 };
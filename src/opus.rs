@@ -0,0 +1,497 @@
+//! Parsing of the Ogg Opus headers, as defined by RFC 7845.
+
+use std::convert::TryInto;
+
+use crate::comment::{find_comment, parse_comment_list, write_comment_list};
+
+/// The magic bytes of the identification header.
+const OPUS_HEAD_MAGIC: &[u8] = b"OpusHead";
+/// The magic bytes of the comment header.
+const OPUS_TAGS_MAGIC: &[u8] = b"OpusTags";
+
+/// The size of the identification header without the channel mapping table.
+const OPUS_HEAD_SIZE: usize = 19;
+
+/// The longest duration of a packet in samples at 48 kHz (120 ms).
+const MAX_PACKET_DURATION: u32 = 5760;
+
+/// The identification header of an Ogg Opus logical bitstream, which is its BOS packet.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OpusHead {
+    version: u8,
+    channel_count: u8,
+    pre_skip: u16,
+    input_sample_rate: u32,
+    output_gain: i16,
+    channel_mapping_family: u8,
+    stream_count: u8,
+    coupled_stream_count: u8,
+    channel_mapping: Vec<u8>,
+}
+
+/// The source of an output channel inside the Opus streams of a multistream packet.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum OpusChannel {
+    /// The left or right channel of a coupled stereo stream.
+    Coupled {
+        /// The index of the stream.
+        stream: u8,
+        /// The channel is the right channel of the stream.
+        is_right: bool,
+    },
+    /// The channel of an uncoupled mono stream.
+    Uncoupled {
+        /// The index of the stream.
+        stream: u8,
+    },
+    /// The channel is silent.
+    Silence,
+}
+
+impl OpusHead {
+    /// Creates a new identification header of a mono or stereo logical bitstream.
+    ///
+    /// Returns `None` if the channel count is not 1 or 2.
+    pub fn new(channel_count: u8, pre_skip: u16, input_sample_rate: u32) -> Option<Self> {
+        let coupled_stream_count = channel_count.checked_sub(1)?;
+        Self::with_mapping(
+            0,
+            channel_count,
+            1,
+            coupled_stream_count,
+            Vec::new(),
+            pre_skip,
+        )
+        .map(|head| head.with_input_sample_rate(input_sample_rate))
+    }
+
+    /// Creates a new identification header with an explicit channel mapping table, which maps
+    /// the output channels to the decoded channels of the streams.
+    ///
+    /// Returns `None` if the channel mapping is invalid.
+    pub fn with_mapping(
+        channel_mapping_family: u8,
+        channel_count: u8,
+        stream_count: u8,
+        coupled_stream_count: u8,
+        channel_mapping: Vec<u8>,
+        pre_skip: u16,
+    ) -> Option<Self> {
+        let head = Self {
+            version: 1,
+            channel_count,
+            pre_skip,
+            input_sample_rate: 0,
+            output_gain: 0,
+            channel_mapping_family,
+            stream_count,
+            coupled_stream_count,
+            channel_mapping,
+        };
+
+        // The table is validated like a parsed one.
+        Self::parse(&head.to_packet())
+    }
+
+    /// Parses an identification header.
+    ///
+    /// Returns `None` if the packet is not an identification header, has an unsupported
+    /// major version or an invalid channel mapping.
+    pub fn parse(packet: &[u8]) -> Option<Self> {
+        if !packet.starts_with(OPUS_HEAD_MAGIC) || packet.len() < OPUS_HEAD_SIZE {
+            return None;
+        }
+
+        // Only the major version 0 is compatible.
+        let version = packet[8];
+        if version & 0xF0 != 0 {
+            return None;
+        }
+
+        let channel_count = packet[9];
+        let channel_mapping_family = packet[18];
+        if channel_count == 0 {
+            return None;
+        }
+
+        let (stream_count, coupled_stream_count, channel_mapping) = if channel_mapping_family == 0 {
+            // Mono or stereo with an implicit mapping.
+            match channel_count {
+                1 => (1, 0, vec![0]),
+                2 => (1, 1, vec![0, 1]),
+                _ => return None,
+            }
+        } else {
+            let stream_count = *packet.get(19)?;
+            let coupled_stream_count = *packet.get(20)?;
+            let channel_mapping = packet.get(21..21 + usize::from(channel_count))?.to_vec();
+
+            let decoded_channels = u16::from(stream_count) + u16::from(coupled_stream_count);
+            if stream_count == 0
+                || coupled_stream_count > stream_count
+                || decoded_channels > 255
+                || (channel_mapping_family == 1 && channel_count > 8)
+                || channel_mapping
+                    .iter()
+                    .any(|index| *index != 255 && u16::from(*index) >= decoded_channels)
+            {
+                return None;
+            }
+
+            (stream_count, coupled_stream_count, channel_mapping)
+        };
+
+        Some(Self {
+            version,
+            channel_count,
+            pre_skip: u16::from_le_bytes(packet[10..12].try_into().ok()?),
+            input_sample_rate: u32::from_le_bytes(packet[12..16].try_into().ok()?),
+            output_gain: i16::from_le_bytes(packet[16..18].try_into().ok()?),
+            channel_mapping_family,
+            stream_count,
+            coupled_stream_count,
+            channel_mapping,
+        })
+    }
+
+    /// The version of the identification header.
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    /// The number of output channels.
+    pub fn channel_count(&self) -> u8 {
+        self.channel_count
+    }
+
+    /// The number of samples at 48 kHz to discard from the decoder output when starting
+    /// playback.
+    pub fn pre_skip(&self) -> u16 {
+        self.pre_skip
+    }
+
+    /// The sample rate of the original input, which is only informational.
+    ///
+    /// Is `0` if it's unspecified.
+    pub fn input_sample_rate(&self) -> u32 {
+        self.input_sample_rate
+    }
+
+    /// The gain to apply to the decoded output in dB, as Q7.8 fixed point number.
+    pub fn output_gain(&self) -> i16 {
+        self.output_gain
+    }
+
+    /// The gain to apply to the decoded output in dB.
+    pub fn output_gain_db(&self) -> f32 {
+        f32::from(self.output_gain) / 256.0
+    }
+
+    /// The channel mapping family, which defines the order and meaning of the channels.
+    pub fn channel_mapping_family(&self) -> u8 {
+        self.channel_mapping_family
+    }
+
+    /// The number of Opus streams in each packet.
+    pub fn stream_count(&self) -> u8 {
+        self.stream_count
+    }
+
+    /// The number of Opus streams that are coupled stereo streams.
+    pub fn coupled_stream_count(&self) -> u8 {
+        self.coupled_stream_count
+    }
+
+    /// The index of the decoded channel of each output channel. Is implicit for the channel
+    /// mapping family 0.
+    pub fn channel_mapping(&self) -> &[u8] {
+        self.channel_mapping.as_ref()
+    }
+
+    /// Sets the sample rate of the original input.
+    pub fn with_input_sample_rate(mut self, input_sample_rate: u32) -> Self {
+        self.input_sample_rate = input_sample_rate;
+        self
+    }
+
+    /// Sets the gain to apply to the decoded output in dB, as Q7.8 fixed point number.
+    pub fn set_output_gain(&mut self, output_gain: i16) {
+        self.output_gain = output_gain;
+    }
+
+    /// Returns the identification header packet.
+    pub fn to_packet(&self) -> Vec<u8> {
+        let mut packet = Vec::with_capacity(OPUS_HEAD_SIZE + 2 + self.channel_mapping.len());
+        packet.extend_from_slice(OPUS_HEAD_MAGIC);
+        packet.push(self.version);
+        packet.push(self.channel_count);
+        packet.extend_from_slice(&self.pre_skip.to_le_bytes());
+        packet.extend_from_slice(&self.input_sample_rate.to_le_bytes());
+        packet.extend_from_slice(&self.output_gain.to_le_bytes());
+        packet.push(self.channel_mapping_family);
+        if self.channel_mapping_family != 0 {
+            packet.push(self.stream_count);
+            packet.push(self.coupled_stream_count);
+            packet.extend_from_slice(&self.channel_mapping);
+        }
+        packet
+    }
+
+    /// Returns the source of the given output channel.
+    pub fn channel(&self, channel: usize) -> Option<OpusChannel> {
+        let index = *self.channel_mapping.get(channel)?;
+        let coupled_channels = self.coupled_stream_count * 2;
+
+        let source = if index == 255 {
+            OpusChannel::Silence
+        } else if index < coupled_channels {
+            OpusChannel::Coupled {
+                stream: index / 2,
+                is_right: index % 2 == 1,
+            }
+        } else {
+            OpusChannel::Uncoupled {
+                stream: index - self.coupled_stream_count,
+            }
+        };
+
+        Some(source)
+    }
+}
+
+/// The comment header of an Ogg Opus logical bitstream.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OpusTags {
+    vendor: String,
+    comments: Vec<(String, String)>,
+    binary_data: Vec<u8>,
+}
+
+impl OpusTags {
+    /// Creates a new comment header with the given vendor string.
+    pub fn new(vendor: &str) -> Self {
+        Self {
+            vendor: vendor.to_owned(),
+            comments: Vec::new(),
+            binary_data: Vec::new(),
+        }
+    }
+
+    /// Parses a comment header.
+    ///
+    /// Returns `None` if the packet is not a comment header or is truncated. User comments
+    /// without a `=` are ignored.
+    pub fn parse(packet: &[u8]) -> Option<Self> {
+        let data = packet.strip_prefix(OPUS_TAGS_MAGIC)?;
+        let (vendor, comments, binary_data) = parse_comment_list(data)?;
+
+        Some(Self {
+            vendor,
+            comments,
+            binary_data: binary_data.to_vec(),
+        })
+    }
+
+    /// The vendor string of the encoder.
+    pub fn vendor(&self) -> &str {
+        self.vendor.as_str()
+    }
+
+    /// The user comments as tag and value pairs, like `ARTIST` and its name.
+    pub fn comments(&self) -> &[(String, String)] {
+        self.comments.as_ref()
+    }
+
+    /// Returns the value of the first user comment with the given tag, which is compared
+    /// case insensitive.
+    pub fn comment(&self, tag: &str) -> Option<&str> {
+        find_comment(&self.comments, tag)
+    }
+
+    /// The data after the user comments. Should be preserved by editors if the least
+    /// significant bit of its first byte is set.
+    pub fn binary_data(&self) -> &[u8] {
+        self.binary_data.as_ref()
+    }
+
+    /// Adds a user comment.
+    pub fn add_comment(&mut self, tag: &str, value: &str) {
+        self.comments.push((tag.to_owned(), value.to_owned()));
+    }
+
+    /// Returns the comment header packet.
+    ///
+    /// Returns `None` if a string is too long.
+    pub fn to_packet(&self) -> Option<Vec<u8>> {
+        let mut packet = OPUS_TAGS_MAGIC.to_vec();
+        write_comment_list(&mut packet, &self.vendor, &self.comments)?;
+        packet.extend_from_slice(&self.binary_data);
+        Some(packet)
+    }
+}
+
+/// Returns the duration of an Opus packet in samples at 48 kHz, derived from its TOC byte.
+///
+/// Returns `None` for packets without a valid TOC byte or that are longer than 120 ms.
+pub(crate) fn packet_duration(packet: &[u8]) -> Option<u32> {
+    let toc = *packet.first()?;
+    let config = toc >> 3;
+
+    // The frame size of the SILK, hybrid and CELT configurations.
+    let frame_size = match config {
+        0..=11 => [480, 960, 1920, 2880][usize::from(config % 4)],
+        12..=15 => [480, 960][usize::from(config % 2)],
+        _ => [120, 240, 480, 960][usize::from(config % 4)],
+    };
+
+    let frame_count = match toc & 0x03 {
+        0 => 1,
+        1 | 2 => 2,
+        _ => u32::from(*packet.get(1)? & 0x3F),
+    };
+
+    Some(frame_size * frame_count).filter(|duration| (1..=MAX_PACKET_DURATION).contains(duration))
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::panic)]
+    #![allow(clippy::unwrap_used)]
+
+    use std::convert::TryFrom;
+
+    use super::*;
+
+    #[test]
+    fn test_opus_head() {
+        let packet = b"OpusHead\x01\x02\x38\x01\x44\xAC\x00\x00\x00\xFF\x00";
+        let head = OpusHead::parse(packet).unwrap();
+        assert_eq!(head.version(), 1);
+        assert_eq!(head.channel_count(), 2);
+        assert_eq!(head.pre_skip(), 312);
+        assert_eq!(head.input_sample_rate(), 44_100);
+        assert_eq!(head.output_gain(), -256);
+        assert_eq!(head.output_gain_db(), -1.0);
+        assert_eq!(head.channel_mapping_family(), 0);
+        assert_eq!(head.stream_count(), 1);
+        assert_eq!(head.coupled_stream_count(), 1);
+        assert_eq!(
+            head.channel(1),
+            Some(OpusChannel::Coupled {
+                stream: 0,
+                is_right: true
+            })
+        );
+        assert_eq!(head.channel(2), None);
+
+        // Incompatible major version.
+        let mut invalid = packet.to_vec();
+        invalid[8] = 0x10;
+        assert!(OpusHead::parse(&invalid).is_none());
+
+        // Family 0 only supports mono and stereo.
+        let mut invalid = packet.to_vec();
+        invalid[9] = 3;
+        assert!(OpusHead::parse(&invalid).is_none());
+
+        assert!(OpusHead::parse(&packet[..18]).is_none());
+    }
+
+    #[test]
+    fn test_opus_head_multistream() {
+        // 5.1 surround with 4 streams, 2 of them coupled.
+        let mut packet = b"OpusHead\x01\x06\x38\x01\x80\xBB\x00\x00\x00\x00\x01".to_vec();
+        packet.extend_from_slice(&[4, 2, 0, 4, 1, 2, 3, 5]);
+        let head = OpusHead::parse(&packet).unwrap();
+        assert_eq!(head.stream_count(), 4);
+        assert_eq!(head.coupled_stream_count(), 2);
+        assert_eq!(head.channel_mapping(), &[0, 4, 1, 2, 3, 5]);
+        assert_eq!(
+            head.channel(0),
+            Some(OpusChannel::Coupled {
+                stream: 0,
+                is_right: false
+            })
+        );
+        assert_eq!(head.channel(1), Some(OpusChannel::Uncoupled { stream: 2 }));
+        assert_eq!(head.channel(5), Some(OpusChannel::Uncoupled { stream: 3 }));
+
+        let mut silent = packet.clone();
+        silent[23] = 255;
+        let head = OpusHead::parse(&silent).unwrap();
+        assert_eq!(head.channel(2), Some(OpusChannel::Silence));
+
+        // The mapping references a channel that isn't decoded.
+        let mut invalid = packet.clone();
+        invalid[26] = 6;
+        assert!(OpusHead::parse(&invalid).is_none());
+
+        // More coupled streams than streams.
+        let mut invalid = packet.clone();
+        invalid[20] = 5;
+        assert!(OpusHead::parse(&invalid).is_none());
+
+        assert!(OpusHead::parse(&packet[..25]).is_none());
+    }
+
+    #[test]
+    fn test_opus_tags() {
+        let mut packet = b"OpusTags".to_vec();
+        packet.extend_from_slice(&7_u32.to_le_bytes());
+        packet.extend_from_slice(b"libopus");
+        packet.extend_from_slice(&3_u32.to_le_bytes());
+        for comment in [&b"ARTIST=Someone"[..], b"invalid", b"title=A=B"].iter() {
+            packet.extend_from_slice(&u32::try_from(comment.len()).unwrap().to_le_bytes());
+            packet.extend_from_slice(comment);
+        }
+        packet.extend_from_slice(&[0x01, 0xAA]);
+
+        let tags = OpusTags::parse(&packet).unwrap();
+        assert_eq!(tags.vendor(), "libopus");
+        assert_eq!(tags.comments().len(), 2);
+        assert_eq!(tags.comment("artist"), Some("Someone"));
+        assert_eq!(tags.comment("TITLE"), Some("A=B"));
+        assert_eq!(tags.comment("ALBUM"), None);
+        assert_eq!(tags.binary_data(), &[0x01, 0xAA]);
+
+        assert!(OpusTags::parse(&packet[..30]).is_none());
+        assert!(OpusTags::parse(b"OpusHead").is_none());
+    }
+
+    #[test]
+    fn test_to_packet() {
+        let packet = b"OpusHead\x01\x02\x38\x01\x44\xAC\x00\x00\x00\xFF\x00";
+        let mut head = OpusHead::new(2, 312, 44_100).unwrap();
+        head.set_output_gain(-256);
+        assert_eq!(head.to_packet(), packet.to_vec());
+        assert!(OpusHead::new(0, 312, 44_100).is_none());
+        assert!(OpusHead::new(3, 312, 44_100).is_none());
+
+        let head = OpusHead::with_mapping(1, 6, 4, 2, vec![0, 4, 1, 2, 3, 5], 312).unwrap();
+        assert_eq!(OpusHead::parse(&head.to_packet()).unwrap(), head);
+        assert!(OpusHead::with_mapping(1, 6, 4, 2, vec![0, 4, 1, 2, 3, 6], 312).is_none());
+        assert!(OpusHead::with_mapping(1, 6, 4, 2, vec![0, 4, 1], 312).is_none());
+
+        let mut tags = OpusTags::new("libopus");
+        tags.add_comment("ARTIST", "Someone");
+        let tags = OpusTags::parse(&tags.to_packet().unwrap()).unwrap();
+        assert_eq!(tags.vendor(), "libopus");
+        assert_eq!(tags.comment("artist"), Some("Someone"));
+    }
+
+    #[test]
+    fn test_packet_duration() {
+        // SILK 10 ms, hybrid 20 ms and CELT 2.5 ms frames.
+        assert_eq!(packet_duration(&[0x00]), Some(480));
+        assert_eq!(packet_duration(&[0x68]), Some(960));
+        assert_eq!(packet_duration(&[0x80]), Some(120));
+        // SILK 60 ms with two frames.
+        assert_eq!(packet_duration(&[0x19]), Some(5760));
+        // CELT 20 ms with an arbitrary number of frames.
+        assert_eq!(packet_duration(&[0xFB, 0x85]), Some(4800));
+        assert_eq!(packet_duration(&[0xFB, 0x07]), None);
+        assert_eq!(packet_duration(&[0xFB, 0x00]), None);
+        assert_eq!(packet_duration(&[0xFB]), None);
+        assert_eq!(packet_duration(&[]), None);
+    }
+}
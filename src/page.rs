@@ -0,0 +1,219 @@
+//! OGG pages.
+
+use std::convert::TryFrom;
+
+use crate::{BOS_VALUE, CONTINUATION_VALUE, EOS_VALUE, SEGMENT_TABLE_INDEX};
+
+/// A page inside an OGG stream.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Page {
+    /// The version of the bitstream format.
+    version: u8,
+    /// The header type flags.
+    header_type: u8,
+    /// The granule position of the last packet finished on this page.
+    granule_position: u64,
+    /// Unique serial ID of the logical bitstream this page belongs to.
+    bitstream_serial_number: u32,
+    /// The sequence number of the page inside the logical bitstream.
+    page_sequence_number: u32,
+    /// The CRC32 checksum of the page.
+    crc32: u32,
+    /// The lacing values of the segment table.
+    segment_table: Vec<u8>,
+    /// The payload of the page.
+    data: Vec<u8>,
+}
+
+impl Page {
+    /// Creates a new page with the given segment table and payload. The header fields are
+    /// set by the setters.
+    ///
+    /// The lacing values need to add up to the size of the payload, otherwise writing the
+    /// page fails.
+    pub fn new(segment_table: Vec<u8>, data: Vec<u8>) -> Self {
+        Self {
+            segment_table,
+            data,
+            ..Default::default()
+        }
+    }
+
+    /// Creates a new page that contains the given complete packets. The header fields are
+    /// set by the setters.
+    ///
+    /// Returns `None` if the packets need more than 255 segments.
+    pub fn from_packets(packets: &[&[u8]]) -> Option<Self> {
+        let mut segment_table = Vec::new();
+        let mut data = Vec::new();
+        for packet in packets.iter() {
+            // Packets whose size is a multiple of 255 end with an empty segment.
+            segment_table.resize(segment_table.len() + packet.len() / 255, 255);
+            segment_table.push(u8::try_from(packet.len() % 255).ok()?);
+            data.extend_from_slice(packet);
+        }
+
+        if segment_table.len() > 255 {
+            return None;
+        }
+
+        Some(Self::new(segment_table, data))
+    }
+
+    /// The version of the bitstream format. Always `0` for valid pages.
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    /// The raw header type flags.
+    pub fn header_type(&self) -> u8 {
+        self.header_type
+    }
+
+    /// Page continues a packet of the previous page.
+    pub fn is_continuation(&self) -> bool {
+        self.header_type & CONTINUATION_VALUE != 0
+    }
+
+    /// Page has a begin of stream marker.
+    pub fn is_bos(&self) -> bool {
+        self.header_type & BOS_VALUE != 0
+    }
+
+    /// Page has a end of stream marker.
+    pub fn is_eos(&self) -> bool {
+        self.header_type & EOS_VALUE != 0
+    }
+
+    /// The granule position of the last packet finished on this page.
+    ///
+    /// Is `u64::MAX` if no packet finishes on this page.
+    pub fn granule_position(&self) -> u64 {
+        self.granule_position
+    }
+
+    /// Unique serial ID of the logical bitstream this page belongs to.
+    pub fn bitstream_serial_number(&self) -> u32 {
+        self.bitstream_serial_number
+    }
+
+    /// The sequence number of the page inside the logical bitstream.
+    pub fn page_sequence_number(&self) -> u32 {
+        self.page_sequence_number
+    }
+
+    /// The CRC32 checksum as stored in the page header.
+    pub fn checksum(&self) -> u32 {
+        self.crc32
+    }
+
+    /// The lacing values of the segment table.
+    pub fn segment_table(&self) -> &[u8] {
+        self.segment_table.as_ref()
+    }
+
+    /// The payload of the page.
+    pub fn data(&self) -> &[u8] {
+        self.data.as_ref()
+    }
+
+    /// The size of the page in bytes, including the header.
+    pub fn size(&self) -> usize {
+        SEGMENT_TABLE_INDEX + self.segment_table.len() + self.data.len()
+    }
+
+    /// Sets the raw header type flags.
+    pub fn set_header_type(&mut self, header_type: u8) {
+        self.header_type = header_type;
+    }
+
+    /// Sets the granule position of the last packet finished on this page.
+    pub fn set_granule_position(&mut self, granule_position: u64) {
+        self.granule_position = granule_position;
+    }
+
+    /// Sets the unique serial ID of the logical bitstream this page belongs to.
+    pub fn set_bitstream_serial_number(&mut self, bitstream_serial_number: u32) {
+        self.bitstream_serial_number = bitstream_serial_number;
+    }
+
+    /// Sets the sequence number of the page inside the logical bitstream.
+    pub fn set_page_sequence_number(&mut self, page_sequence_number: u32) {
+        self.page_sequence_number = page_sequence_number;
+    }
+
+    /// Returns true if the segment table is consistent with the payload.
+    #[cfg(feature = "writer")]
+    pub(crate) fn is_valid(&self) -> bool {
+        let payload_size: usize = self
+            .segment_table
+            .iter()
+            .map(|lace| usize::from(*lace))
+            .sum();
+
+        self.version == 0 && self.segment_table.len() <= 255 && payload_size == self.data.len()
+    }
+
+    /// Serializes the page into the given buffer and returns the size of the page.
+    ///
+    /// The CRC32 checksum is always recalculated. The page needs to be valid.
+    #[cfg(feature = "writer")]
+    pub(crate) fn write_raw(
+        &self,
+        page_buffer: &mut [u8],
+    ) -> Result<usize, std::num::TryFromIntError> {
+        use crate::crc32::crc32;
+        use crate::{
+            BITSTREAM_SERIAL_NUMBER_RANGE, CRC32_RANGE, GRANULE_POSITION_RANGE, HEADER_TYPE_INDEX,
+            PAGER_MARKER, PAGER_MARKER_RANGE, PAGE_SEQUENCE_NUMBER_RANGE, SEGMENT_COUNT_INDEX,
+            VERSION_INDEX,
+        };
+
+        let data_start = SEGMENT_TABLE_INDEX + self.segment_table.len();
+        let data_end = data_start + self.data.len();
+
+        page_buffer[PAGER_MARKER_RANGE].copy_from_slice(&PAGER_MARKER);
+        page_buffer[VERSION_INDEX] = self.version;
+        page_buffer[HEADER_TYPE_INDEX] = self.header_type;
+        page_buffer[GRANULE_POSITION_RANGE].copy_from_slice(&self.granule_position.to_le_bytes());
+        page_buffer[BITSTREAM_SERIAL_NUMBER_RANGE]
+            .copy_from_slice(&self.bitstream_serial_number.to_le_bytes());
+        page_buffer[PAGE_SEQUENCE_NUMBER_RANGE]
+            .copy_from_slice(&self.page_sequence_number.to_le_bytes());
+        page_buffer[CRC32_RANGE].copy_from_slice(&[0, 0, 0, 0]);
+        page_buffer[SEGMENT_COUNT_INDEX] = u8::try_from(self.segment_table.len())?;
+        page_buffer[SEGMENT_TABLE_INDEX..data_start].copy_from_slice(&self.segment_table);
+        page_buffer[data_start..data_end].copy_from_slice(&self.data);
+
+        let crc32 = crc32(&page_buffer[..data_end]);
+        page_buffer[CRC32_RANGE].copy_from_slice(&crc32.to_le_bytes());
+
+        Ok(data_end)
+    }
+
+    /// Fills the page with the data of a complete and verified raw page.
+    #[cfg(feature = "reader")]
+    pub(crate) fn fill_from_raw(&mut self, raw_page: &[u8]) {
+        use crate::{
+            parse_u32_le, parse_u64_le, BITSTREAM_SERIAL_NUMBER_RANGE, CRC32_RANGE,
+            GRANULE_POSITION_RANGE, HEADER_TYPE_INDEX, PAGE_SEQUENCE_NUMBER_RANGE,
+            SEGMENT_COUNT_INDEX, VERSION_INDEX,
+        };
+
+        let table_size = usize::from(raw_page[SEGMENT_COUNT_INDEX]);
+        let table_end = SEGMENT_TABLE_INDEX + table_size;
+
+        self.version = raw_page[VERSION_INDEX];
+        self.header_type = raw_page[HEADER_TYPE_INDEX];
+        self.granule_position = parse_u64_le(&raw_page[GRANULE_POSITION_RANGE]);
+        self.bitstream_serial_number = parse_u32_le(&raw_page[BITSTREAM_SERIAL_NUMBER_RANGE]);
+        self.page_sequence_number = parse_u32_le(&raw_page[PAGE_SEQUENCE_NUMBER_RANGE]);
+        self.crc32 = parse_u32_le(&raw_page[CRC32_RANGE]);
+
+        self.segment_table.clear();
+        self.segment_table
+            .extend_from_slice(&raw_page[SEGMENT_TABLE_INDEX..table_end]);
+        self.data.clear();
+        self.data.extend_from_slice(&raw_page[table_end..]);
+    }
+}
@@ -13,6 +13,16 @@ pub enum ReadError {
     UnhandledBitstreamVersion(u8),
     /// Unable to sync.
     UnableToSync,
+    /// The EOF was reached inside a page.
+    TruncatedPage,
+    /// Unknown bitstream serial number.
+    UnknownBitstreamSerialNumber,
+    /// The granule positions of the codec can't be mapped to time.
+    UnsupportedCodec,
+    /// The serialized seek index is invalid.
+    InvalidSeekIndex,
+    /// A header packet of the logical bitstream is invalid.
+    InvalidHeader,
 }
 
 impl std::fmt::Display for ReadError {
@@ -34,6 +44,21 @@ impl std::fmt::Display for ReadError {
             ReadError::UnableToSync => {
                 write!(f, "can't sync the next page")
             }
+            ReadError::TruncatedPage => {
+                write!(f, "reached the EOF inside a page")
+            }
+            ReadError::UnknownBitstreamSerialNumber => {
+                write!(f, "unknown bitstream serial number")
+            }
+            ReadError::UnsupportedCodec => {
+                write!(f, "granule positions of the codec can't be mapped to time")
+            }
+            ReadError::InvalidSeekIndex => {
+                write!(f, "the serialized seek index is invalid")
+            }
+            ReadError::InvalidHeader => {
+                write!(f, "a header packet of the logical bitstream is invalid")
+            }
         }
     }
 }
@@ -1,21 +1,22 @@
-use std::collections::VecDeque;
+use std::cmp::Ordering;
+use std::collections::{HashMap, VecDeque};
 use std::convert::TryFrom;
-use std::error::Error;
-use std::io::{ErrorKind, Read, Seek, SeekFrom, Write};
+use std::io::{ErrorKind, Read, Seek, SeekFrom};
 use std::ops::Range;
+use std::time::Duration;
 
 use crate::crc32::crc32;
 use crate::{
-    parse_u32_le, parse_u64_le, ReadError, BITSTREAM_SERIAL_NUMBER_RANGE, BOS_VALUE,
-    CONST_HEADER_DATA_RANGE, CONTINUATION_VALUE, CRC32_RANGE, EOS_VALUE, GRANULE_POSITION_RANGE,
-    HEADER_RANGE, HEADER_TYPE_INDEX, MAX_PAGE_SIZE, PAGER_MARKER, PAGE_SEQUENCE_NUMBER_RANGE,
-    SEGMENT_COUNT_INDEX, SEGMENT_TABLE_INDEX, VERSION_INDEX,
+    parse_u32_le, parse_u64_le, Codec, GranuleMapper, Page, ReadError,
+    BITSTREAM_SERIAL_NUMBER_RANGE, BOS_VALUE, CONTINUATION_VALUE, CRC32_RANGE, EOS_VALUE,
+    GRANULE_POSITION_RANGE, HEADER_RANGE, HEADER_TYPE_INDEX, MAX_PAGE_SIZE, PAGER_MARKER,
+    PAGE_SEQUENCE_NUMBER_RANGE, SEGMENT_COUNT_INDEX, SEGMENT_TABLE_INDEX, VERSION_INDEX,
 };
 
 macro_rules! handle_eof {
     ($err:ident, $action:expr) => {
-        if let Some(err) = $err.source() {
-            if err.downcast_ref::<std::io::Error>().is_some() {
+        if let ReadError::IoError(err) = &$err {
+            if err.kind() == ErrorKind::UnexpectedEof {
                 $action;
             }
         }
@@ -23,6 +24,18 @@ macro_rules! handle_eof {
     };
 }
 
+#[cfg(feature = "async")]
+mod async_reader;
+mod chain;
+mod opus;
+mod seek_index;
+
+#[cfg(feature = "async")]
+pub use async_reader::AsyncStreamReader;
+pub use chain::{Chain, StreamInfo};
+pub use opus::{OpusPacket, OpusReader};
+pub use seek_index::SeekIndex;
+
 /// A packet inside an OGG stream.
 #[derive(Clone, Debug, Default)]
 pub struct Packet {
@@ -36,6 +49,8 @@ pub struct Packet {
     is_bos: bool,
     /// Paket is a end of stream marker.
     is_eos: bool,
+    /// Paket is the last packet that is completed on its page.
+    is_last_on_page: bool,
 }
 
 impl Packet {
@@ -63,17 +78,91 @@ impl Packet {
     pub fn is_eos(&self) -> bool {
         self.is_eos
     }
+
+    /// Paket is the last packet that is completed on its page, which means that the granule
+    /// position marks the end of this packet.
+    pub fn is_last_on_page(&self) -> bool {
+        self.is_last_on_page
+    }
+
+    /// The codec of the logical bitstream if the packet has a begin of stream marker.
+    pub fn codec(&self) -> Option<Codec> {
+        if self.is_bos {
+            Some(Codec::detect(&self.data))
+        } else {
+            None
+        }
+    }
+}
+
+/// The position a seek landed on.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct SeekResult {
+    byte_offset: u64,
+    granule_position: Option<u64>,
+    page_sequence_number: Option<u32>,
+    is_clamped: bool,
+}
+
+impl SeekResult {
+    /// The byte offset the reader was positioned at.
+    pub fn byte_offset(&self) -> u64 {
+        self.byte_offset
+    }
+
+    /// The granule position of the page of the next complete packet after the byte offset.
+    ///
+    /// Is `None` if there is no packet of the logical bitstream after the byte offset.
+    pub fn granule_position(&self) -> Option<u64> {
+        self.granule_position
+    }
+
+    /// The sequence number of the page of the next complete packet after the byte offset.
+    ///
+    /// Is `None` if there is no packet of the logical bitstream after the byte offset.
+    pub fn page_sequence_number(&self) -> Option<u32> {
+        self.page_sequence_number
+    }
+
+    /// The target was after the end of the logical bitstream.
+    pub fn is_clamped(&self) -> bool {
+        self.is_clamped
+    }
 }
 
 /// Returns the status of the read operation.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum ReadStatus {
-    /// Paket was written.
+    /// Paket or page was written.
     Ok,
     /// No new packet, since we reached the EOF.
     Eof,
-    /// No new packet. Page was corrupted or page didn't contain any packet.
+    /// No new packet, since more data needs to be provided to the reader.
+    NeedMoreData,
+    /// No new packet. Page was corrupted or a packet was lost.
     Missing,
+    /// No new packet. Pages of a logical bitstream were lost, which was detected by a gap
+    /// in the page sequence numbers. The packets of the page after the gap are returned
+    /// by the following reads.
+    Lost {
+        /// Unique serial ID of the logical bitstream that lost pages.
+        bitstream_serial_number: u32,
+        /// The number of pages that were lost.
+        missing_pages: u32,
+    },
+}
+
+/// Status of a page that was read by the `BitStreamReader`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum PageStatus {
+    /// The packets of the page were queued.
+    Queued,
+    /// The page was already read and got dropped.
+    Duplicate,
+    /// The packets of the page were queued, but a packet of the logical bitstream was lost.
+    PacketLost,
+    /// The packets of the page were queued, but pages before it were lost.
+    PagesLost(u32),
 }
 
 #[derive(Clone, Debug)]
@@ -87,6 +176,8 @@ struct QueuedPacket {
 pub struct FileReader<R: Read + Seek> {
     inner: BitStreamReader,
     reader: R,
+    chains: Option<Vec<Chain>>,
+    file_end: u64,
 }
 
 impl<R: Read + Seek> FileReader<R> {
@@ -95,6 +186,8 @@ impl<R: Read + Seek> FileReader<R> {
         Self {
             inner: Default::default(),
             reader,
+            chains: None,
+            file_end: 0,
         }
     }
 
@@ -108,30 +201,425 @@ impl<R: Read + Seek> FileReader<R> {
     /// Will gracefully handle recoverable errors like pages with wrong checksums,
     /// missing packets and out of sync events.
     ///
-    /// Returns the status of the operation. When receiving `ReadStatus::MissingPacket` a page
-    /// was corrupt / invalid and no data was written into the given packet.
+    /// Returns the status of the operation. When receiving `ReadStatus::Missing` a page
+    /// was corrupt / invalid and no data was written into the given packet. When receiving
+    /// `ReadStatus::Lost` pages of a logical bitstream were lost.
+    ///
+    /// Non-blocking readers are supported. When the reader returns an I/O error of the kind
+    /// `WouldBlock`, `ReadStatus::NeedMoreData` is returned instead of the error. The data read
+    /// so far is kept and reading resumes at the same position with the next call. Reads that
+    /// were `Interrupted` are retried.
+    ///
+    /// Only reaching the EOF in between pages returns `ReadStatus::Eof`. Reaching it inside
+    /// a page returns `ReadError::TruncatedPage`, all other I/O errors are returned as is.
     pub fn next_packet(&mut self, packet: &mut Packet) -> Result<ReadStatus, ReadError> {
         self.inner.next_packet(&mut self.reader, packet)
     }
 
+    /// Reads the next page from the reader.
+    ///
+    /// Pages are returned as they are found in the physical bitstream, without any packet
+    /// reassembly. Packets that were queued or partially read by `next_packet()` are discarded.
+    ///
+    /// Returns the status of the operation. When receiving `ReadStatus::Missing` a page
+    /// was corrupt / invalid and no data was written into the given page.
+    ///
+    /// Non-blocking readers are supported. When the reader returns an I/O error of the kind
+    /// `WouldBlock`, `ReadStatus::NeedMoreData` is returned instead of the error. The data read
+    /// so far is kept and reading resumes at the same position with the next call. Reads that
+    /// were `Interrupted` are retried.
+    ///
+    /// Only reaching the EOF in between pages returns `ReadStatus::Eof`. Reaching it inside
+    /// a page returns `ReadError::TruncatedPage`, all other I/O errors are returned as is.
+    pub fn next_page(&mut self, page: &mut Page) -> Result<ReadStatus, ReadError> {
+        self.inner.next_page(&mut self.reader, page)
+    }
+
     /// Seeks to the first page that has an granule position greater or equal
     /// to th given one for the given logical bitstream.
     ///
-    /// Does not support seeking in chained files (like live stream recordings).
+    /// In chained files (like live stream recordings) the search is limited to the first
+    /// chain of the logical bitstream whose granule positions reach the target. If the granule
+    /// positions restart in every chain, `seek_to_time()` selects the chain by its playback
+    /// time instead. If no chain contains the logical bitstream (because its BOS page is
+    /// missing), the whole file is searched.
+    ///
+    /// If the chain has a Skeleton index for the logical bitstream, the search starts at the
+    /// last keypoint at or before the target. The granule position is mapped to the time of
+    /// the keypoints by the granule rate of the fisbone of the logical bitstream.
     ///
     /// If the user is seeking outside of the stream, `read_packet()`
-    /// will return the packets of the last page.
+    /// will return the packets of the last page and the result is marked as clamped.
     pub fn seek(
         &mut self,
         bitstream_serial_number: u32,
         target_granule_position: u64,
-    ) -> Result<(), ReadError> {
-        self.inner.seek(
+    ) -> Result<SeekResult, ReadError> {
+        let order = |granule_position| granule_position;
+        let chain = self.find_chain(bitstream_serial_number, target_granule_position, &order)?;
+        self.seek_granule(
+            chain,
+            bitstream_serial_number,
+            target_granule_position,
+            &order,
+        )
+    }
+
+    /// Seeks to the page that contains the keyframe the given granule position depends on.
+    ///
+    /// Granule positions are compared and split into keyframes by the given granule mapper,
+    /// which allows seeking in codecs with split granule positions like Theora. Decoding can
+    /// start cleanly with the first packet after the seek. For codecs without keyframes this
+    /// behaves like `seek()`.
+    ///
+    /// If the chain has a Skeleton index for the logical bitstream, the page of its last
+    /// keypoint at or before the target is used instead of searching the keyframe.
+    pub fn seek_keyframe(
+        &mut self,
+        bitstream_serial_number: u32,
+        target_granule_position: u64,
+        mapper: &dyn GranuleMapper,
+    ) -> Result<SeekResult, ReadError> {
+        let order = |granule_position| mapper.granule_order(granule_position);
+        let chain = self.find_chain(bitstream_serial_number, target_granule_position, &order)?;
+        self.seek_keyframe_in_chain(
+            chain,
+            bitstream_serial_number,
+            target_granule_position,
+            mapper,
+        )
+    }
+
+    /// Seeks to the keyframe of the given logical bitstream for the given time.
+    ///
+    /// The granule mapper is chosen by the codec of the logical bitstream. Returns
+    /// `ReadError::UnsupportedCodec` if there is no granule mapper for the codec.
+    pub fn seek_to_time(
+        &mut self,
+        bitstream_serial_number: u32,
+        time: Duration,
+    ) -> Result<SeekResult, ReadError> {
+        let mapper = self.granule_mapper(bitstream_serial_number)?;
+        self.seek_to_time_with(bitstream_serial_number, time, mapper.as_ref())
+    }
+
+    /// Seeks to the keyframe of the given logical bitstream for the given time, using the
+    /// given granule mapper.
+    ///
+    /// The time is measured from the start of the file. If the granule positions of the
+    /// logical bitstream restart in a chain, the chain starts at the end of the chain before.
+    pub fn seek_to_time_with(
+        &mut self,
+        bitstream_serial_number: u32,
+        time: Duration,
+        mapper: &dyn GranuleMapper,
+    ) -> Result<SeekResult, ReadError> {
+        let (chain, target_granule_position) =
+            self.find_chain_by_time(bitstream_serial_number, time, mapper)?;
+        self.seek_keyframe_in_chain(
+            chain,
+            bitstream_serial_number,
+            target_granule_position,
+            mapper,
+        )
+    }
+
+    /// Seeks like `seek()`, but lands the given amount of granules before the target, so that
+    /// codecs that need pre-roll (like Opus or Vorbis) can decode the target correctly.
+    ///
+    /// The granule position of the page the reader landed on is part of the result.
+    pub fn seek_with_pre_roll(
+        &mut self,
+        bitstream_serial_number: u32,
+        target_granule_position: u64,
+        pre_roll: u64,
+    ) -> Result<SeekResult, ReadError> {
+        self.seek(
+            bitstream_serial_number,
+            target_granule_position.saturating_sub(pre_roll),
+        )
+    }
+
+    /// Seeks like `seek_to_time()`, but lands the given pre-roll before the target time.
+    ///
+    /// The granule position of the page the reader landed on is part of the result.
+    pub fn seek_to_time_with_pre_roll(
+        &mut self,
+        bitstream_serial_number: u32,
+        time: Duration,
+        pre_roll: Duration,
+    ) -> Result<SeekResult, ReadError> {
+        let mapper = self.granule_mapper(bitstream_serial_number)?;
+        self.seek_to_time_with(
+            bitstream_serial_number,
+            time.saturating_sub(pre_roll),
+            mapper.as_ref(),
+        )
+    }
+
+    /// Seeks to the target inside of the chain with the given index.
+    fn seek_granule(
+        &mut self,
+        chain: Option<usize>,
+        bitstream_serial_number: u32,
+        target_granule_position: u64,
+        order: &dyn Fn(u64) -> u64,
+    ) -> Result<SeekResult, ReadError> {
+        let (mut byte_range, last_granule_position) =
+            self.chain_range(chain, bitstream_serial_number);
+
+        // If the chain has a Skeleton index for the logical bitstream, the search starts at
+        // its last keypoint at or before the target.
+        let chains = self.chains.as_deref().unwrap_or_default();
+        let keypoint_offset = chain
+            .and_then(|index| chains.get(index))
+            .filter(|_| target_granule_position != 0 && target_granule_position != u64::MAX)
+            .and_then(|chain| {
+                chain.keypoint_offset_of_granule(bitstream_serial_number, target_granule_position)
+            });
+        if let Some(byte_offset) = keypoint_offset {
+            byte_range.start = byte_offset;
+        }
+
+        let result = self.inner.seek(
             &mut self.reader,
+            byte_range,
             bitstream_serial_number,
             target_granule_position,
+            order,
+        )?;
+
+        self.seek_finished(
+            result,
+            chain,
+            bitstream_serial_number,
+            target_granule_position,
+            last_granule_position.map(order),
+            order(target_granule_position),
         )
     }
+
+    /// Seeks to the keyframe of the target inside of the chain with the given index.
+    fn seek_keyframe_in_chain(
+        &mut self,
+        chain: Option<usize>,
+        bitstream_serial_number: u32,
+        target_granule_position: u64,
+        mapper: &dyn GranuleMapper,
+    ) -> Result<SeekResult, ReadError> {
+        let order = |granule_position| mapper.granule_order(granule_position);
+        let (byte_range, last_granule_position) = self.chain_range(chain, bitstream_serial_number);
+        let last_order = last_granule_position.map(order);
+        let target_order = order(target_granule_position);
+
+        let chains = self.chains.as_deref().unwrap_or_default();
+        let keypoint_offset = Some(target_granule_position)
+            .filter(|granule_position| *granule_position != u64::MAX)
+            .and_then(|granule_position| mapper.granule_to_time(granule_position))
+            .zip(chain.and_then(|index| chains.get(index)))
+            .and_then(|(time, chain)| chain.keypoint_offset(bitstream_serial_number, time));
+
+        if let Some(byte_offset) = keypoint_offset {
+            let result = self.inner.seek_to_page(
+                &mut self.reader,
+                byte_offset,
+                bitstream_serial_number,
+                byte_range.end,
+            )?;
+
+            return self.seek_finished(
+                result,
+                chain,
+                bitstream_serial_number,
+                target_granule_position,
+                last_order,
+                target_order,
+            );
+        }
+
+        let mut result = self.inner.seek(
+            &mut self.reader,
+            byte_range.clone(),
+            bitstream_serial_number,
+            target_granule_position,
+            &order,
+        )?;
+
+        if let Some(granule_position) = result.granule_position {
+            if let Some(mut keyframe_granule_position) = mapper.keyframe_granule(granule_position) {
+                // The keyframe of the page we landed on is the keyframe of the target, if it's
+                // not after the target. Otherwise the keyframe of the page before is used,
+                // which is the same or an earlier one.
+                if order(keyframe_granule_position) > target_order {
+                    keyframe_granule_position = self
+                        .inner
+                        .last_granule_position_before(
+                            &mut self.reader,
+                            bitstream_serial_number,
+                            byte_range.start,
+                            result.byte_offset,
+                        )?
+                        .and_then(|granule_position| mapper.keyframe_granule(granule_position))
+                        .unwrap_or(0);
+                }
+
+                result = self.inner.seek(
+                    &mut self.reader,
+                    byte_range,
+                    bitstream_serial_number,
+                    keyframe_granule_position,
+                    &order,
+                )?;
+            }
+        }
+
+        self.seek_finished(
+            result,
+            chain,
+            bitstream_serial_number,
+            target_granule_position,
+            last_order,
+            target_order,
+        )
+    }
+
+    /// Returns the byte range of the chain with the given index and the last granule position
+    /// of the logical bitstream inside of it. Without a chain the whole file is used.
+    fn chain_range(
+        &self,
+        chain: Option<usize>,
+        bitstream_serial_number: u32,
+    ) -> (Range<u64>, Option<u64>) {
+        let chains = self.chains.as_deref().unwrap_or_default();
+        match chain.and_then(|index| chains.get(index)) {
+            Some(chain) => (
+                chain.byte_range(),
+                chain
+                    .stream(bitstream_serial_number)
+                    .and_then(StreamInfo::last_granule_position),
+            ),
+            None => (0..self.file_end, None),
+        }
+    }
+
+    /// Marks the result as clamped if the target is after the last granule position of the
+    /// logical bitstream inside the chain.
+    ///
+    /// Without a chain, the logical bitstream is unknown if none of its pages were found.
+    fn seek_finished(
+        &mut self,
+        mut result: SeekResult,
+        chain: Option<usize>,
+        bitstream_serial_number: u32,
+        target_granule_position: u64,
+        last_order: Option<u64>,
+        target_order: u64,
+    ) -> Result<SeekResult, ReadError> {
+        if last_order.map_or(false, |last_order| target_order > last_order) {
+            result.is_clamped = true;
+        }
+
+        if chain.is_none()
+            && result.granule_position.is_none()
+            && target_granule_position != u64::MAX
+        {
+            // The search only misses the logical bitstream if the target is after all of its
+            // pages, which are then searched from the start of the file.
+            let byte_offset = result.byte_offset;
+            let first = self.inner.seek_to_page(
+                &mut self.reader,
+                0,
+                bitstream_serial_number,
+                self.file_end,
+            )?;
+            if first.granule_position.is_none() {
+                return Err(ReadError::UnknownBitstreamSerialNumber);
+            }
+            self.reader.seek(SeekFrom::Start(byte_offset))?;
+        }
+
+        Ok(result)
+    }
+
+    /// Returns the granule mapper of the codec of the logical bitstream.
+    fn granule_mapper(
+        &mut self,
+        bitstream_serial_number: u32,
+    ) -> Result<Box<dyn GranuleMapper>, ReadError> {
+        self.streams()?
+            .iter()
+            .find_map(|chain| chain.stream(bitstream_serial_number))
+            .ok_or(ReadError::UnknownBitstreamSerialNumber)?
+            .granule_mapper()
+            .ok_or(ReadError::UnsupportedCodec)
+    }
+
+    /// Returns the index of the chain to search for the target. Is `None` if no chain
+    /// contains the logical bitstream.
+    fn find_chain(
+        &mut self,
+        bitstream_serial_number: u32,
+        target_granule_position: u64,
+        order: &dyn Fn(u64) -> u64,
+    ) -> Result<Option<usize>, ReadError> {
+        let candidates = self
+            .streams()?
+            .iter()
+            .enumerate()
+            .filter_map(|(index, chain)| {
+                let stream = chain.stream(bitstream_serial_number)?;
+                Some((index, stream.last_granule_position().map(order)))
+            });
+
+        Ok(chain::find_chain(
+            candidates,
+            &order(target_granule_position),
+        ))
+    }
+
+    /// Returns the index of the chain to search for the given time together with the granule
+    /// position of the time inside of the chain.
+    ///
+    /// Chains whose granule positions restart are placed after the end of the chain before.
+    fn find_chain_by_time(
+        &mut self,
+        bitstream_serial_number: u32,
+        time: Duration,
+        mapper: &dyn GranuleMapper,
+    ) -> Result<(Option<usize>, u64), ReadError> {
+        let mut candidates = Vec::new();
+        let mut offset = Duration::from_secs(0);
+        let mut previous_end: Option<Duration> = None;
+        for (index, chain) in self.streams()?.iter().enumerate() {
+            let stream = match chain.stream(bitstream_serial_number) {
+                Some(stream) => stream,
+                None => continue,
+            };
+            let start = stream.start_granule_position().map(|granule_position| {
+                mapper.granule_to_time(granule_position).unwrap_or_default()
+            });
+            let end = stream
+                .last_granule_position()
+                .and_then(|granule_position| mapper.granule_to_time(granule_position));
+
+            if let (Some(previous_end), Some(start)) = (previous_end, start) {
+                if start < previous_end {
+                    offset = offset.saturating_add(previous_end);
+                }
+            }
+            previous_end = end;
+
+            candidates.push(((index, offset), end.map(|end| end.saturating_add(offset))));
+        }
+
+        Ok(match chain::find_chain(candidates, &time) {
+            Some((index, offset)) => (
+                Some(index),
+                mapper.time_to_granule(time.saturating_sub(offset)),
+            ),
+            None => (None, mapper.time_to_granule(time)),
+        })
+    }
 }
 
 /// Generic OGG stream reader.
@@ -160,21 +648,153 @@ impl<R: Read> StreamReader<R> {
     /// Will gracefully handle recoverable errors like pages with wrong checksums,
     /// missing packets and out of sync events.
     ///
-    /// Returns the status of the operation. When receiving `ReadStatus::MissingPacket` a page
-    /// was corrupt / invalid and no data was written into the given packet.
+    /// Returns the status of the operation. When receiving `ReadStatus::Missing` a page
+    /// was corrupt / invalid and no data was written into the given packet. When receiving
+    /// `ReadStatus::Lost` pages of a logical bitstream were lost.
+    ///
+    /// Non-blocking readers are supported. When the reader returns an I/O error of the kind
+    /// `WouldBlock`, `ReadStatus::NeedMoreData` is returned instead of the error. The data read
+    /// so far is kept and reading resumes at the same position with the next call. Reads that
+    /// were `Interrupted` are retried.
+    ///
+    /// Only reaching the EOF in between pages returns `ReadStatus::Eof`. Reaching it inside
+    /// a page returns `ReadError::TruncatedPage`, all other I/O errors are returned as is.
     pub fn next_packet(&mut self, packet: &mut Packet) -> Result<ReadStatus, ReadError> {
         self.inner.next_packet(&mut self.reader, packet)
     }
+
+    /// Reads the next page from the reader.
+    ///
+    /// Pages are returned as they are found in the physical bitstream, without any packet
+    /// reassembly. Packets that were queued or partially read by `next_packet()` are discarded.
+    ///
+    /// Returns the status of the operation. When receiving `ReadStatus::Missing` a page
+    /// was corrupt / invalid and no data was written into the given page.
+    ///
+    /// Non-blocking readers are supported. When the reader returns an I/O error of the kind
+    /// `WouldBlock`, `ReadStatus::NeedMoreData` is returned instead of the error. The data read
+    /// so far is kept and reading resumes at the same position with the next call. Reads that
+    /// were `Interrupted` are retried.
+    ///
+    /// Only reaching the EOF in between pages returns `ReadStatus::Eof`. Reaching it inside
+    /// a page returns `ReadError::TruncatedPage`, all other I/O errors are returned as is.
+    pub fn next_page(&mut self, page: &mut Page) -> Result<ReadStatus, ReadError> {
+        self.inner.next_page(&mut self.reader, page)
+    }
+}
+
+/// Generic OGG push reader.
+///
+/// Doesn't perform any I/O itself. The caller pushes the data of the physical bitstream in
+/// chunks of arbitrary size into the reader and reads the complete pages or packets out of it.
+#[derive(Clone, Debug, Default)]
+pub struct PushReader {
+    inner: BitStreamReader,
+    buffer: Vec<u8>,
+    buffer_head: usize,
+}
+
+impl PushReader {
+    /// Creates a new `PushReader`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Pushes the given data of the physical bitstream into the reader.
+    pub fn push(&mut self, data: &[u8]) {
+        self.buffer.drain(..self.buffer_head);
+        self.buffer_head = 0;
+        self.buffer.extend_from_slice(data);
+    }
+
+    /// Returns the amount of pushed bytes that the reader hasn't consumed yet.
+    pub fn buffered_bytes(&self) -> usize {
+        self.buffer.len() - self.buffer_head
+    }
+
+    /// Reads the next packet from the pushed data.
+    ///
+    /// Will gracefully handle recoverable errors like pages with wrong checksums,
+    /// missing packets and out of sync events.
+    ///
+    /// Returns the status of the operation. When receiving `ReadStatus::NeedMoreData` all
+    /// pushed data was consumed and more data needs to be pushed to read the next packet.
+    pub fn next_packet(&mut self, packet: &mut Packet) -> Result<ReadStatus, ReadError> {
+        packet.data.clear();
+
+        loop {
+            if self.inner.pop_packet(packet) {
+                return Ok(ReadStatus::Ok);
+            }
+
+            let page_size = match self.read_page()? {
+                Some(page_size) => page_size,
+                None => return Ok(ReadStatus::NeedMoreData),
+            };
+
+            if let Some(status) = self.inner.queue_page(page_size)? {
+                return Ok(status);
+            }
+        }
+    }
+
+    /// Reads the next page from the pushed data.
+    ///
+    /// Pages are returned as they are found in the physical bitstream, without any packet
+    /// reassembly. Packets that were queued or partially read by `next_packet()` are discarded.
+    ///
+    /// Returns the status of the operation. When receiving `ReadStatus::NeedMoreData` all
+    /// pushed data was consumed and more data needs to be pushed to read the next page.
+    pub fn next_page(&mut self, page: &mut Page) -> Result<ReadStatus, ReadError> {
+        self.inner.discard_packets();
+
+        match self.read_page()? {
+            Some(page_size) => self.inner.write_page(page_size, page),
+            None => Ok(ReadStatus::NeedMoreData),
+        }
+    }
+
+    /// Moves the pushed data into the page buffer. Returns the size of the page once it's
+    /// complete.
+    fn read_page(&mut self) -> Result<Option<usize>, ReadError> {
+        loop {
+            let pending = &self.buffer[self.buffer_head..];
+            let unfilled = self.inner.unfilled_page_buffer();
+
+            let size = usize::min(unfilled.len(), pending.len());
+            if size == 0 {
+                return Ok(None);
+            }
+
+            unfilled[..size].copy_from_slice(&pending[..size]);
+            self.buffer_head += size;
+
+            if let Some(page_size) = self.inner.advance_page_buffer(size)? {
+                return Ok(Some(page_size));
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct LogicalStreamState {
+    /// Data of an unfinished packet that is continued on the next page.
+    partial_packet: Vec<u8>,
+    /// The sequence number of the last page read.
+    page_sequence_number: Option<u32>,
 }
 
 #[derive(Clone, Debug)]
 struct BitStreamReader {
     page_buffer: Box<[u8]>,
     queued_packets: VecDeque<QueuedPacket>,
+    stream_states: HashMap<u32, LogicalStreamState>,
     current_bitstream_serial_number: u32,
-    current_page_sequence_number: u32,
     current_granule_position: u64,
+    current_is_bos: bool,
     current_is_eos: bool,
+    page_head: usize,
+    skipped_bytes: usize,
 }
 
 impl Default for BitStreamReader {
@@ -182,10 +802,13 @@ impl Default for BitStreamReader {
         Self {
             page_buffer: vec![0_u8; 65_307].into_boxed_slice(),
             queued_packets: VecDeque::with_capacity(32),
+            stream_states: HashMap::with_capacity(4),
             current_bitstream_serial_number: 0,
-            current_page_sequence_number: 0,
             current_granule_position: 0,
+            current_is_bos: false,
             current_is_eos: false,
+            page_head: 0,
+            skipped_bytes: 0,
         }
     }
 }
@@ -198,138 +821,262 @@ impl BitStreamReader {
     ) -> Result<ReadStatus, ReadError> {
         packet.data.clear();
 
-        let is_last_packet = self.queued_packets.len() == 1;
-        if let Some(queued_packet) = self.queued_packets.pop_front() {
-            self.write_frame(packet, queued_packet.range)?;
-
-            if is_last_packet && self.current_is_eos {
-                packet.is_eos = true;
-            }
-
-            if queued_packet.is_complete {
-                return Ok(ReadStatus::Ok);
-            }
-        }
-
         loop {
-            if let Err(err) = self.sync_with_next_page(reader) {
-                handle_eof!(err, return Ok(ReadStatus::Eof));
+            if self.pop_packet(packet) {
+                return Ok(ReadStatus::Ok);
             }
 
-            let page_size = match self.read_page_data(reader) {
-                Ok(page_size) => page_size,
+            let page_size = match self.read_page(reader) {
+                Ok(Some(page_size)) => page_size,
+                Ok(None) => return Ok(ReadStatus::NeedMoreData),
                 Err(err) => {
                     handle_eof!(err, return Ok(ReadStatus::Eof));
                 }
             };
 
-            if !self.verify_crc32(page_size) {
-                self.queued_packets.clear();
-                packet.data.clear();
+            if let Some(status) = self.queue_page(page_size)? {
+                return Ok(status);
+            }
+        }
+    }
 
-                return Ok(ReadStatus::Missing);
+    fn next_page<R: Read>(
+        &mut self,
+        reader: &mut R,
+        page: &mut Page,
+    ) -> Result<ReadStatus, ReadError> {
+        self.discard_packets();
+
+        let page_size = match self.read_page(reader) {
+            Ok(Some(page_size)) => page_size,
+            Ok(None) => return Ok(ReadStatus::NeedMoreData),
+            Err(err) => {
+                handle_eof!(err, return Ok(ReadStatus::Eof));
             }
+        };
 
-            let version = self.page_buffer[VERSION_INDEX];
+        self.write_page(page_size, page)
+    }
 
-            let header_type = self.page_buffer[HEADER_TYPE_INDEX];
-            let granule_position = parse_u64_le(&self.page_buffer[GRANULE_POSITION_RANGE]);
+    /// Discards all queued and partially read packets.
+    fn discard_packets(&mut self) {
+        self.queued_packets.clear();
+        self.stream_states.clear();
+    }
 
-            let bitstream_serial_number =
-                parse_u32_le(&self.page_buffer[BITSTREAM_SERIAL_NUMBER_RANGE]);
-            let page_sequence_number = parse_u32_le(&self.page_buffer[PAGE_SEQUENCE_NUMBER_RANGE]);
+    /// Writes the complete page inside the page buffer into the given page.
+    fn write_page(&mut self, page_size: usize, page: &mut Page) -> Result<ReadStatus, ReadError> {
+        if !self.verify_page(page_size)? {
+            return Ok(ReadStatus::Missing);
+        }
 
-            let is_continuation = header_type & CONTINUATION_VALUE == 1;
-            let is_bos = (header_type & BOS_VALUE) >> 1 == 1;
-            let is_eos = (header_type & EOS_VALUE) >> 2 == 1;
+        page.fill_from_raw(&self.page_buffer[..page_size]);
 
-            if version != 0 {
-                return Err(ReadError::UnhandledBitstreamVersion(version));
-            }
+        Ok(ReadStatus::Ok)
+    }
 
-            self.current_bitstream_serial_number = bitstream_serial_number;
-            self.current_granule_position = granule_position;
-            self.current_is_eos = is_eos;
-
-            // Make sure we only append data to a previous, unfinished packet, if the page sequence
-            // is sequential and the packet is from the same bitstream.
-            if !packet.data.is_empty()
-                && (self.current_bitstream_serial_number != bitstream_serial_number
-                    || (self.current_page_sequence_number + 1) > page_sequence_number)
-            {
-                packet.data.clear();
-            }
+    /// Verifies the complete page inside the page buffer and queues its packets. Returns the
+    /// status that needs to be reported, if the page can't be read as expected.
+    fn queue_page(&mut self, page_size: usize) -> Result<Option<ReadStatus>, ReadError> {
+        if !self.verify_page(page_size)? {
+            return Ok(Some(ReadStatus::Missing));
+        }
 
-            return if let Some(queued_packet) = self.queued_packets.pop_front() {
-                // Make sure we are actually appending to an unfinished packet.
-                if is_continuation && !packet.data.is_empty() {
-                    return Ok(ReadStatus::Missing);
-                }
+        let status = match self.process_page() {
+            PageStatus::Queued | PageStatus::Duplicate => None,
+            PageStatus::PacketLost => Some(ReadStatus::Missing),
+            PageStatus::PagesLost(missing_pages) => Some(ReadStatus::Lost {
+                bitstream_serial_number: self.current_bitstream_serial_number,
+                missing_pages,
+            }),
+        };
 
-                self.write_frame(packet, queued_packet.range)?;
+        Ok(status)
+    }
 
-                if !queued_packet.is_complete {
-                    continue;
-                }
+    /// Returns false if the checksum of the complete page inside the page buffer doesn't match.
+    fn verify_page(&mut self, page_size: usize) -> Result<bool, ReadError> {
+        if !self.verify_crc32(page_size) {
+            return Ok(false);
+        }
 
-                if is_bos {
-                    packet.is_bos = true;
-                }
+        let version = self.page_buffer[VERSION_INDEX];
+        if version != 0 {
+            return Err(ReadError::UnhandledBitstreamVersion(version));
+        }
 
-                Ok(ReadStatus::Ok)
-            } else {
-                Ok(ReadStatus::Missing)
-            };
+        Ok(true)
+    }
+
+    /// Returns the part of the page buffer that needs to be filled next to complete the
+    /// current page. Is empty if the current page is complete.
+    fn unfilled_page_buffer(&mut self) -> &mut [u8] {
+        let page_target = self.page_target();
+        &mut self.page_buffer[self.page_head..page_target]
+    }
+
+    /// Returns the size the current page has, as far as it is known by the data read so far.
+    fn page_target(&self) -> usize {
+        if self.page_head < PAGER_MARKER.len() {
+            return PAGER_MARKER.len();
         }
+
+        if self.page_head < SEGMENT_TABLE_INDEX {
+            return SEGMENT_TABLE_INDEX;
+        }
+
+        let table_end = SEGMENT_TABLE_INDEX + usize::from(self.page_buffer[SEGMENT_COUNT_INDEX]);
+        if self.page_head < table_end {
+            return table_end;
+        }
+
+        let payload_size: usize = self.page_buffer[SEGMENT_TABLE_INDEX..table_end]
+            .iter()
+            .map(|lace| usize::from(*lace))
+            .sum();
+
+        table_end + payload_size
     }
 
-    fn write_frame(
-        &mut self,
-        packet: &mut Packet,
-        data_range: Range<usize>,
-    ) -> Result<(), ReadError> {
-        packet.data.write_all(&self.page_buffer[data_range])?;
-        packet.bitstream_serial_number = self.current_bitstream_serial_number;
-        packet.granule_position = self.current_granule_position;
-        packet.is_bos = false;
-        packet.is_eos = false;
+    /// Marks the given amount of bytes of the unfilled page buffer as filled. Returns the size
+    /// of the page once it is complete.
+    ///
+    /// Data in front of the pager marker is skipped to sync with the next page.
+    fn advance_page_buffer(&mut self, read: usize) -> Result<Option<usize>, ReadError> {
+        self.page_head += read;
+
+        if self.page_head <= PAGER_MARKER.len() {
+            // Keep the longest tail, that could be the start of a pager marker.
+            let marker_start = (0..self.page_head)
+                .find(|start| PAGER_MARKER.starts_with(&self.page_buffer[*start..self.page_head]))
+                .unwrap_or(self.page_head);
+
+            self.page_buffer
+                .copy_within(marker_start..self.page_head, 0);
+            self.page_head -= marker_start;
+            self.skipped_bytes += marker_start;
+
+            if self.skipped_bytes > MAX_PAGE_SIZE {
+                self.skipped_bytes = 0;
+                return Err(ReadError::UnableToSync);
+            }
+
+            if self.page_head == PAGER_MARKER.len() {
+                self.skipped_bytes = 0;
+            }
+
+            return Ok(None);
+        }
 
-        Ok(())
+        if self.page_head == self.page_target() {
+            let page_size = self.page_head;
+            self.page_head = 0;
+            return Ok(Some(page_size));
+        }
+
+        Ok(None)
     }
 
-    fn sync_with_next_page<R: Read>(&self, reader: &mut R) -> Result<(), ReadError> {
-        let mut buffer = [0_u8; 4];
+    /// Queues the packets of the verified page inside the page buffer and updates the state of
+    /// its logical bitstream.
+    fn process_page(&mut self) -> PageStatus {
+        let header_type = self.page_buffer[HEADER_TYPE_INDEX];
+        let granule_position = parse_u64_le(&self.page_buffer[GRANULE_POSITION_RANGE]);
+        let bitstream_serial_number =
+            parse_u32_le(&self.page_buffer[BITSTREAM_SERIAL_NUMBER_RANGE]);
+        let page_sequence_number = parse_u32_le(&self.page_buffer[PAGE_SEQUENCE_NUMBER_RANGE]);
+
+        let is_continuation = header_type & CONTINUATION_VALUE != 0;
+        let is_bos = header_type & BOS_VALUE != 0;
+        let is_eos = header_type & EOS_VALUE != 0;
 
-        // Fast path.
-        reader.read_exact(&mut buffer)?;
-        if buffer == PAGER_MARKER {
-            return Ok(());
+        // Chained files can reuse the serial number of an already ended logical bitstream.
+        if is_bos {
+            self.stream_states.remove(&bitstream_serial_number);
         }
 
-        // Count matches.
-        let mut marker_found = 0;
-        for byte in &buffer {
-            if *byte == PAGER_MARKER[marker_found] {
-                marker_found += 1;
-            } else {
-                marker_found = 0;
-            }
+        let state = self
+            .stream_states
+            .entry(bitstream_serial_number)
+            .or_default();
+
+        // The distance is calculated with wrapping arithmetic, so that the wraparound of the
+        // page sequence number is handled. Distances in the upper half are pages from the past.
+        let missing_pages = match state.page_sequence_number {
+            Some(number) => match page_sequence_number.wrapping_sub(number) {
+                0 => return PageStatus::Duplicate,
+                distance if distance > u32::MAX / 2 => return PageStatus::Duplicate,
+                distance => distance - 1,
+            },
+            None => 0,
+        };
+        state.page_sequence_number = Some(page_sequence_number);
+
+        // Make sure we only append data to a previous, unfinished packet, if the page sequence
+        // is sequential and the page actually continues the packet.
+        let is_continued = is_continuation && missing_pages == 0;
+        let mut is_packet_lost = false;
+        if !state.partial_packet.is_empty() && !is_continued {
+            state.partial_packet.clear();
+            is_packet_lost = true;
         }
+        let drop_first_packet = is_continuation && state.partial_packet.is_empty();
 
-        // Re-sync.
-        for _ in 0..MAX_PAGE_SIZE {
-            if marker_found == 4 {
-                return Ok(());
-            }
-            reader.read_exact(&mut buffer[..1])?;
-            if buffer[0] == PAGER_MARKER[marker_found] {
-                marker_found += 1;
-            } else {
-                marker_found = 0;
+        self.current_bitstream_serial_number = bitstream_serial_number;
+        self.current_granule_position = granule_position;
+        self.current_is_bos = is_bos;
+        self.current_is_eos = is_eos;
+
+        self.queue_packets();
+
+        // The start of the continued packet is unknown, so we drop its remainder.
+        if drop_first_packet {
+            self.queued_packets.pop_front();
+            is_packet_lost = true;
+        }
+
+        if missing_pages != 0 {
+            PageStatus::PagesLost(missing_pages)
+        } else if is_packet_lost {
+            PageStatus::PacketLost
+        } else {
+            PageStatus::Queued
+        }
+    }
+
+    /// Writes the next queued packet into the given packet. Unfinished packets are saved in
+    /// the state of their logical bitstream. Returns true if a packet was written.
+    fn pop_packet(&mut self, packet: &mut Packet) -> bool {
+        while let Some(queued_packet) = self.queued_packets.pop_front() {
+            let state = self
+                .stream_states
+                .entry(self.current_bitstream_serial_number)
+                .or_default();
+
+            state
+                .partial_packet
+                .extend_from_slice(&self.page_buffer[queued_packet.range]);
+
+            if !queued_packet.is_complete {
+                continue;
             }
+
+            std::mem::swap(&mut packet.data, &mut state.partial_packet);
+            state.partial_packet.clear();
+
+            packet.bitstream_serial_number = self.current_bitstream_serial_number;
+            packet.granule_position = self.current_granule_position;
+            packet.is_bos = self.current_is_bos;
+            packet.is_eos = self.current_is_eos && self.queued_packets.is_empty();
+            packet.is_last_on_page = self.queued_packets.iter().all(|queued| !queued.is_complete);
+
+            // Only the first packet of a page can be the begin of stream marker.
+            self.current_is_bos = false;
+
+            return true;
         }
 
-        Err(ReadError::UnableToSync)
+        false
     }
 
     fn verify_crc32(&mut self, page_size: usize) -> bool {
@@ -340,21 +1087,55 @@ impl BitStreamReader {
 
         let crc32 = crc32(&self.page_buffer[..page_size]);
 
+        // Restore the checksum, so that the page can be handed out unaltered.
+        self.page_buffer[CRC32_RANGE].copy_from_slice(&target_crc.to_le_bytes());
+
         target_crc == crc32
     }
 
-    fn read_page_data<R: Read>(&mut self, reader: &mut R) -> Result<usize, ReadError> {
-        PAGER_MARKER
-            .iter()
-            .enumerate()
-            .for_each(|(i, x)| self.page_buffer[i] = *x);
-        reader.read_exact(&mut self.page_buffer[CONST_HEADER_DATA_RANGE])?;
+    /// Reads data from the reader into the page buffer. Returns the size of the page once it's
+    /// complete.
+    ///
+    /// Returns `None` if the reader would block. The data read so far is kept, so that reading
+    /// can be resumed with the next call. Interrupted reads are retried.
+    ///
+    /// Reaching the EOF in between pages returns an `UnexpectedEof` I/O error. Reaching it
+    /// inside a page returns `ReadError::TruncatedPage`.
+    fn read_page<R: Read>(&mut self, reader: &mut R) -> Result<Option<usize>, ReadError> {
+        loop {
+            let read = match reader.read(self.unfilled_page_buffer()) {
+                Ok(0) => return Err(self.eof_error()),
+                Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Err(self.eof_error()),
+                Ok(read) => read,
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) if err.kind() == ErrorKind::WouldBlock => return Ok(None),
+                Err(err) => return Err(err.into()),
+            };
+
+            if let Some(page_size) = self.advance_page_buffer(read)? {
+                return Ok(Some(page_size));
+            }
+        }
+    }
 
-        // Read the packet offsets from the segment table.
+    /// Returns the error for reaching the EOF at the current position inside the page.
+    fn eof_error(&self) -> ReadError {
+        // A partially read pager marker could also be trailing data after the last page.
+        if self.page_head >= PAGER_MARKER.len() {
+            ReadError::TruncatedPage
+        } else {
+            ReadError::IoError(std::io::Error::new(
+                ErrorKind::UnexpectedEof,
+                "EOF while syncing with the next page",
+            ))
+        }
+    }
+
+    /// Queues the packets of the page inside the page buffer.
+    fn queue_packets(&mut self) {
         let table_size = usize::from(self.page_buffer[SEGMENT_COUNT_INDEX]);
         let table_start = SEGMENT_TABLE_INDEX;
         let table_end = SEGMENT_TABLE_INDEX + table_size;
-        reader.read_exact(&mut self.page_buffer[table_start..table_end])?;
 
         let mut segment_size = 0;
         let mut read_size = 0;
@@ -384,47 +1165,46 @@ impl BitStreamReader {
                 range: table_end + read_size..table_end + read_size + segment_size,
                 is_complete: false,
             };
-            read_size += segment_size;
 
             self.queued_packets.push_back(queued_packet);
         }
-
-        // Copy the payload data.
-        let page_end = table_start + table_size + read_size;
-        reader.read_exact(&mut self.page_buffer[table_end..page_end])?;
-
-        Ok(page_end)
     }
 
     fn seek<R: Read + Seek>(
         &mut self,
         reader: &mut R,
+        byte_range: Range<u64>,
         bitstream_serial_number: u32,
         target_granule_position: u64,
-    ) -> Result<(), ReadError> {
+        order: &dyn Fn(u64) -> u64,
+    ) -> Result<SeekResult, ReadError> {
         // We assume that packets that spawn multiple pages end in their own page without
         // any other packets in that page.
         // This is currently the behavior the major media mappings (vorbis, opus, flac).
         // Packets only span multiple pages if they are bigger than the maximum allowed
         // packet site.
-        self.queued_packets.clear();
+        self.discard_packets();
+        self.page_head = 0;
+        self.skipped_bytes = 0;
 
         if target_granule_position == u64::MAX {
-            reader.seek(SeekFrom::End(0))?;
-            return Ok(());
+            reader.seek(SeekFrom::Start(byte_range.end))?;
+            return Ok(SeekResult {
+                byte_offset: byte_range.end,
+                is_clamped: true,
+                ..Default::default()
+            });
         }
 
         if target_granule_position == 0 {
-            reader.seek(SeekFrom::Start(0))?;
-            return Ok(());
+            reader.seek(SeekFrom::Start(byte_range.start))?;
+            return self.seek_result(reader, bitstream_serial_number, byte_range.end);
         }
 
-        let max_right = reader.seek(SeekFrom::End(0))?;
+        let mut left = byte_range.start;
+        let mut right = byte_range.end;
 
-        let mut left = 0;
-        let mut right = max_right;
-
-        let mut target = 0;
+        let mut target = byte_range.start;
 
         let mut mid: u64;
         'outer: while left < right {
@@ -434,9 +1214,9 @@ impl BitStreamReader {
 
             let SearchResult {
                 packet_start,
-                packet_end: _,
                 granule_position,
-            } = match self.search_next_packet(reader, bitstream_serial_number) {
+                ..
+            } = match self.search_next_packet(reader, bitstream_serial_number, byte_range.end) {
                 Ok(res) => res,
                 Err(err) => {
                     handle_eof!(err, break 'outer);
@@ -445,40 +1225,100 @@ impl BitStreamReader {
 
             target = packet_start;
 
-            match granule_position {
-                pos if pos < target_granule_position => left = mid.saturating_add(1),
-                pos if pos > target_granule_position => right = mid.saturating_sub(1),
-                _ => break,
+            match order(granule_position).cmp(&order(target_granule_position)) {
+                Ordering::Less => left = mid.saturating_add(1),
+                Ordering::Greater => right = mid.saturating_sub(1),
+                Ordering::Equal => break,
             }
 
             // If the search volume is small enough, we switch to linear search.
-            if (right - left) < 1024 {
+            if right.saturating_sub(left) < 1024 {
                 loop {
                     reader.seek(SeekFrom::Start(left))?;
                     let SearchResult {
-                        packet_start: _,
+                        packet_start,
                         packet_end,
                         granule_position,
-                    } = self.search_next_packet(reader, bitstream_serial_number)?;
-                    if granule_position > target_granule_position {
+                        ..
+                    } = match self.search_next_packet(
+                        reader,
+                        bitstream_serial_number,
+                        byte_range.end,
+                    ) {
+                        Ok(res) => res,
+                        Err(err) => {
+                            handle_eof!(err, break 'outer);
+                        }
+                    };
+                    if order(granule_position) >= order(target_granule_position) {
                         target = left;
                         break 'outer;
                     }
+                    target = packet_start;
                     left = packet_end;
                 }
             }
         }
         reader.seek(SeekFrom::Start(target))?;
 
-        Ok(())
+        self.seek_result(reader, bitstream_serial_number, byte_range.end)
+    }
+
+    /// Seeks to the page at the given offset, which is known to be a valid seek target.
+    fn seek_to_page<R: Read + Seek>(
+        &mut self,
+        reader: &mut R,
+        byte_offset: u64,
+        bitstream_serial_number: u32,
+        end: u64,
+    ) -> Result<SeekResult, ReadError> {
+        self.discard_packets();
+        self.page_head = 0;
+        self.skipped_bytes = 0;
+
+        reader.seek(SeekFrom::Start(byte_offset))?;
+        self.seek_result(reader, bitstream_serial_number, end)
+    }
+
+    /// Returns the result of a seek to the current position, which contains the page of the
+    /// next complete packet of the logical bitstream. The position of the reader is not
+    /// changed.
+    fn seek_result<R: Read + Seek>(
+        &mut self,
+        reader: &mut R,
+        bitstream_serial_number: u32,
+        end: u64,
+    ) -> Result<SeekResult, ReadError> {
+        let byte_offset = reader.stream_position()?;
+
+        let mut result = SeekResult {
+            byte_offset,
+            ..Default::default()
+        };
+
+        match self.search_next_packet(reader, bitstream_serial_number, end) {
+            Ok(res) => {
+                result.granule_position = Some(res.granule_position);
+                result.page_sequence_number = Some(res.page_sequence_number);
+            }
+            Err(ReadError::IoError(err)) if err.kind() == ErrorKind::UnexpectedEof => {}
+            Err(err) => return Err(err),
+        };
+
+        reader.seek(SeekFrom::Start(byte_offset))?;
+
+        Ok(result)
     }
 
     /// Returns the granule position of the next, complete packet. The start and end positions are
     /// the positions that have been searched. A packet can be contained in multiple pages.
+    ///
+    /// Pages starting at or after the given end are treated like the EOF.
     fn search_next_packet<R: Read + Seek>(
         &mut self,
         reader: &mut R,
         bitstream_serial_number: u32,
+        end: u64,
     ) -> Result<SearchResult, ReadError> {
         let mut search_start = reader.stream_position()?;
         let mut packet_start = u64::MAX;
@@ -493,28 +1333,37 @@ impl BitStreamReader {
                 )));
             }
 
-            let mut i = 0;
             let mut marker_found = 0;
-            loop {
-                if i >= read {
-                    search_start += 64 - 3;
-                    reader.seek(SeekFrom::Start(search_start))?;
-                    continue 'outer;
+            for (i, byte) in search_buffer[..read].iter().enumerate() {
+                if *byte == PAGER_MARKER[marker_found] {
+                    marker_found += 1;
+                } else if *byte == PAGER_MARKER[0] {
+                    marker_found = 1;
+                } else {
+                    marker_found = 0;
                 }
 
                 if marker_found == 4 {
-                    let page_start = search_start - 4 + u64::try_from(i)?;
+                    let page_start = search_start + u64::try_from(i)? - 3;
                     let page = self.probe_page(reader, page_start)?;
 
+                    if page.start >= end {
+                        return Err(ReadError::IoError(std::io::Error::new(
+                            ErrorKind::UnexpectedEof,
+                            "reached the end of the chain",
+                        )));
+                    }
+
+                    search_start = page.end;
+                    reader.seek(SeekFrom::Start(search_start))?;
+
                     if page.bitstream_serial_number != bitstream_serial_number {
-                        reader.seek(SeekFrom::Start(page.end))?;
                         continue 'outer;
                     }
 
                     packet_start = u64::min(packet_start, page.start);
 
                     if page.granule_position == u64::MAX {
-                        reader.seek(SeekFrom::Start(page.end))?;
                         continue 'outer;
                     }
 
@@ -522,16 +1371,14 @@ impl BitStreamReader {
                         packet_start,
                         packet_end: page.end,
                         granule_position: page.granule_position,
+                        page_sequence_number: page.page_sequence_number,
                     });
                 }
-                if search_buffer[i] == PAGER_MARKER[marker_found] {
-                    marker_found += 1;
-                } else {
-                    marker_found = 0;
-                }
-
-                i += 1;
             }
+
+            // The last bytes could be the start of a marker.
+            search_start += u64::try_from(usize::max(read.saturating_sub(3), 1))?;
+            reader.seek(SeekFrom::Start(search_start))?;
         }
     }
 
@@ -546,26 +1393,22 @@ impl BitStreamReader {
         let granule_position = parse_u64_le(&self.page_buffer[GRANULE_POSITION_RANGE]);
         let bitstream_serial_number =
             parse_u32_le(&self.page_buffer[BITSTREAM_SERIAL_NUMBER_RANGE]);
+        let page_sequence_number = parse_u32_le(&self.page_buffer[PAGE_SEQUENCE_NUMBER_RANGE]);
         let table_size = usize::from(self.page_buffer[SEGMENT_COUNT_INDEX]);
         let table_start = SEGMENT_TABLE_INDEX;
         let table_end = SEGMENT_TABLE_INDEX + table_size;
         reader.read_exact(&mut self.page_buffer[table_start..table_end])?;
 
-        let mut payload_size = 0;
-        for lace in self.page_buffer[table_start..table_end].iter() {
-            let bytes = usize::from(*lace);
-            match bytes {
-                255 => continue,
-                _ => {
-                    payload_size += bytes;
-                }
-            }
-        }
+        let payload_size: usize = self.page_buffer[table_start..table_end]
+            .iter()
+            .map(|lace| usize::from(*lace))
+            .sum();
         let page_end = page_start + u64::try_from(table_start + table_size + payload_size)?;
 
         Ok(ProbeResult {
             granule_position,
             bitstream_serial_number,
+            page_sequence_number,
             start: page_start,
             end: page_end,
         })
@@ -577,12 +1420,14 @@ struct SearchResult {
     packet_start: u64,
     packet_end: u64,
     granule_position: u64,
+    page_sequence_number: u32,
 }
 
 #[derive(Clone, Debug)]
 struct ProbeResult {
     granule_position: u64,
     bitstream_serial_number: u32,
+    page_sequence_number: u32,
     start: u64,
     end: u64,
 }
@@ -609,7 +1454,8 @@ mod tests {
         let mut br = FileReader::new(c);
         let mut packet = Packet::default();
         let res = br.next_packet(&mut packet).unwrap();
-        assert_eq!(res, ReadStatus::Ok)
+        assert_eq!(res, ReadStatus::Ok);
+        assert_eq!(packet.codec(), Some(Codec::Opus));
     }
 
     #[test]
@@ -628,6 +1474,496 @@ mod tests {
         assert_eq!(res, ReadStatus::Ok)
     }
 
+    #[test]
+    fn test_next_page() {
+        let d: Vec<u8> = vec![
+            0x4F, 0x67, 0x67, 0x53, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x4A, 0xC9, 0x09, 0xB6, 0x00, 0x00, 0x00, 0x00, 0xF9, 0x20, 0x89, 0xF8, 0x01, 0x13,
+            0x4F, 0x70, 0x75, 0x73, 0x48, 0x65, 0x61, 0x64, 0x01, 0x02, 0x38, 0x01, 0x80, 0xBB,
+            0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+        let c = Cursor::new(d.clone());
+
+        let mut sr = StreamReader::new(c);
+        let mut page = Page::default();
+        let res = sr.next_page(&mut page).unwrap();
+        assert_eq!(res, ReadStatus::Ok);
+
+        assert_eq!(page.version(), 0);
+        assert!(page.is_bos());
+        assert!(!page.is_eos());
+        assert!(!page.is_continuation());
+        assert_eq!(page.granule_position(), 0);
+        assert_eq!(page.bitstream_serial_number(), 0xB609C94A);
+        assert_eq!(page.page_sequence_number(), 0);
+        assert_eq!(page.checksum(), 0xF88920F9);
+        assert_eq!(page.segment_table(), &[0x13]);
+        assert_eq!(page.data(), &d[28..]);
+        assert_eq!(page.size(), d.len());
+
+        let res = sr.next_page(&mut page).unwrap();
+        assert_eq!(res, ReadStatus::Eof);
+    }
+
+    /// Writes two logical bitstreams, each with a packet spanning two pages, and returns their
+    /// pages in the order the writer created them.
+    #[cfg(feature = "writer")]
+    fn multiplexed_pages(big_packet_1: &[u8], big_packet_2: &[u8]) -> Vec<Page> {
+        let mut sw = crate::StreamWriter::new(Cursor::new(vec![]));
+        sw.begin_logical_stream(1, &[0x1]).unwrap();
+        sw.begin_logical_stream(2, &[0x2]).unwrap();
+        sw.push_packet(1, big_packet_1, 10).unwrap();
+        sw.push_packet(2, big_packet_2, 20).unwrap();
+        sw.end_logical_stream(1, &[0x3], 30).unwrap();
+        sw.end_logical_stream(2, &[0x4], 40).unwrap();
+
+        let mut sr = StreamReader::new(Cursor::new(sw.into_inner().into_inner()));
+        let mut pages = Vec::new();
+        let mut page = Page::default();
+        while sr.next_page(&mut page).unwrap() == ReadStatus::Ok {
+            pages.push(page.clone());
+        }
+        pages
+    }
+
+    #[cfg(feature = "writer")]
+    fn write_pages(pages: &[&Page]) -> Vec<u8> {
+        let mut sw = crate::StreamWriter::new(Cursor::new(vec![]));
+        for page in pages {
+            sw.write_page(page).unwrap();
+        }
+        sw.into_inner().into_inner()
+    }
+
+    #[test]
+    #[cfg(feature = "writer")]
+    fn test_interleaved_packets() {
+        let big_packet_1 = vec![0xAA; 100_000];
+        let big_packet_2 = vec![0xBB; 90_000];
+        let pages = multiplexed_pages(&big_packet_1, &big_packet_2);
+        assert_eq!(pages.len(), 8);
+
+        // Interleave the pages of the spanning packets.
+        let d = write_pages(&[
+            &pages[0], &pages[1], &pages[2], &pages[4], &pages[3], &pages[5], &pages[6], &pages[7],
+        ]);
+
+        let mut sr = StreamReader::new(Cursor::new(d));
+        let mut packet = Packet::default();
+
+        let expected: [(u32, &[u8], bool, bool); 6] = [
+            (1, &[0x1], true, false),
+            (2, &[0x2], true, false),
+            (1, &big_packet_1, false, false),
+            (2, &big_packet_2, false, false),
+            (1, &[0x3], false, true),
+            (2, &[0x4], false, true),
+        ];
+
+        for (bitstream_serial_number, data, is_bos, is_eos) in expected.iter() {
+            let res = sr.next_packet(&mut packet).unwrap();
+            assert_eq!(res, ReadStatus::Ok);
+            assert_eq!(packet.bitstream_serial_number(), *bitstream_serial_number);
+            assert_eq!(packet.data(), *data);
+            assert_eq!(packet.is_bos(), *is_bos);
+            assert_eq!(packet.is_eos(), *is_eos);
+        }
+
+        let res = sr.next_packet(&mut packet).unwrap();
+        assert_eq!(res, ReadStatus::Eof);
+    }
+
+    #[test]
+    #[cfg(feature = "writer")]
+    fn test_drop_continued_packet_of_lost_page() {
+        let big_packet_1 = vec![0xAA; 100_000];
+        let big_packet_2 = vec![0xBB; 90_000];
+        let pages = multiplexed_pages(&big_packet_1, &big_packet_2);
+
+        // The first page of the spanning packet of the first logical bitstream is lost.
+        let d = write_pages(&[
+            &pages[0], &pages[1], &pages[4], &pages[3], &pages[5], &pages[6], &pages[7],
+        ]);
+
+        let mut sr = StreamReader::new(Cursor::new(d));
+        let mut packet = Packet::default();
+
+        sr.next_packet(&mut packet).unwrap();
+        sr.next_packet(&mut packet).unwrap();
+
+        let res = sr.next_packet(&mut packet).unwrap();
+        assert_eq!(
+            res,
+            ReadStatus::Lost {
+                bitstream_serial_number: 1,
+                missing_pages: 1
+            }
+        );
+
+        let res = sr.next_packet(&mut packet).unwrap();
+        assert_eq!(res, ReadStatus::Ok);
+        assert_eq!(packet.bitstream_serial_number(), 2);
+        assert_eq!(packet.data(), big_packet_2.as_slice());
+
+        let res = sr.next_packet(&mut packet).unwrap();
+        assert_eq!(res, ReadStatus::Ok);
+        assert_eq!(packet.bitstream_serial_number(), 1);
+        assert_eq!(packet.data(), &[0x3]);
+    }
+
+    /// Writes the pages of a logical bitstream with the given page sequence numbers. Each page
+    /// contains a single packet with its index as payload.
+    #[cfg(feature = "writer")]
+    fn sequenced_pages(page_sequence_numbers: &[u32]) -> Vec<u8> {
+        let mut sw = crate::StreamWriter::new(Cursor::new(vec![]));
+        sw.begin_logical_stream(7, &[0]).unwrap();
+        for i in 1..page_sequence_numbers.len() {
+            let index = u8::try_from(i).unwrap();
+            sw.push_packet(7, &[index], u64::from(index)).unwrap();
+            sw.flush(7).unwrap();
+        }
+
+        let mut sr = StreamReader::new(Cursor::new(sw.into_inner().into_inner()));
+        let mut pages = Vec::new();
+        let mut page = Page::default();
+        for page_sequence_number in page_sequence_numbers {
+            sr.next_page(&mut page).unwrap();
+            page.set_page_sequence_number(*page_sequence_number);
+            pages.push(page.clone());
+        }
+
+        write_pages(&pages.iter().collect::<Vec<_>>())
+    }
+
+    #[test]
+    #[cfg(feature = "writer")]
+    fn test_search_next_packet() {
+        let mut sw = crate::StreamWriter::new(Cursor::new(vec![]));
+        sw.begin_logical_stream(1, &[1]).unwrap();
+        sw.begin_logical_stream(2, &[2]).unwrap();
+        sw.push_packet(2, &[0xAA; 100], 20).unwrap();
+        sw.flush(2).unwrap();
+        sw.push_packet(1, &[0xAA; 100], 10).unwrap();
+        sw.flush(1).unwrap();
+        let d = sw.into_inner().into_inner();
+
+        let page_starts: Vec<u64> = (0..d.len())
+            .filter(|i| d[*i..].starts_with(&PAGER_MARKER))
+            .map(|i| u64::try_from(i).unwrap())
+            .collect();
+        assert_eq!(page_starts.len(), 4);
+
+        let mut reader = BitStreamReader::default();
+
+        // The page at the start of the file.
+        let mut cursor = Cursor::new(d.clone());
+        let result = reader.search_next_packet(&mut cursor, 1, u64::MAX).unwrap();
+        assert_eq!(result.packet_start, 0);
+        assert_eq!(result.granule_position, 0);
+
+        // The pages of the other logical bitstream are skipped.
+        cursor.set_position(page_starts[1]);
+        let result = reader.search_next_packet(&mut cursor, 1, u64::MAX).unwrap();
+        assert_eq!(result.packet_start, page_starts[3]);
+        assert_eq!(result.packet_end, u64::try_from(d.len()).unwrap());
+        assert_eq!(result.granule_position, 10);
+
+        // A marker that is split between two reads of the search buffer.
+        let mut shifted = vec![0; 62];
+        shifted.extend_from_slice(&d);
+        let mut cursor = Cursor::new(shifted);
+        let result = reader.search_next_packet(&mut cursor, 1, u64::MAX).unwrap();
+        assert_eq!(result.packet_start, 62);
+    }
+
+    #[test]
+    #[cfg(feature = "writer")]
+    fn test_page_sequence_gap() {
+        let d = sequenced_pages(&[0, 1, 4, 5]);
+
+        let mut sr = StreamReader::new(Cursor::new(d));
+        let mut packet = Packet::default();
+
+        assert_eq!(sr.next_packet(&mut packet).unwrap(), ReadStatus::Ok);
+        assert_eq!(sr.next_packet(&mut packet).unwrap(), ReadStatus::Ok);
+        assert_eq!(
+            sr.next_packet(&mut packet).unwrap(),
+            ReadStatus::Lost {
+                bitstream_serial_number: 7,
+                missing_pages: 2
+            }
+        );
+        assert_eq!(sr.next_packet(&mut packet).unwrap(), ReadStatus::Ok);
+        assert_eq!(packet.data(), &[2]);
+        assert_eq!(sr.next_packet(&mut packet).unwrap(), ReadStatus::Ok);
+        assert_eq!(packet.data(), &[3]);
+        assert_eq!(sr.next_packet(&mut packet).unwrap(), ReadStatus::Eof);
+    }
+
+    #[test]
+    #[cfg(feature = "writer")]
+    fn test_page_sequence_duplicate() {
+        let d = sequenced_pages(&[0, 1, 1, 0, 2]);
+
+        let mut sr = StreamReader::new(Cursor::new(d));
+        let mut packet = Packet::default();
+
+        for expected in &[0, 1, 4] {
+            assert_eq!(sr.next_packet(&mut packet).unwrap(), ReadStatus::Ok);
+            assert_eq!(packet.data(), &[*expected]);
+        }
+        assert_eq!(sr.next_packet(&mut packet).unwrap(), ReadStatus::Eof);
+    }
+
+    #[test]
+    #[cfg(feature = "writer")]
+    fn test_page_sequence_wraparound() {
+        let d = sequenced_pages(&[u32::MAX - 1, u32::MAX, 0, 2]);
+
+        let mut sr = StreamReader::new(Cursor::new(d));
+        let mut packet = Packet::default();
+
+        assert_eq!(sr.next_packet(&mut packet).unwrap(), ReadStatus::Ok);
+        assert_eq!(sr.next_packet(&mut packet).unwrap(), ReadStatus::Ok);
+        assert_eq!(sr.next_packet(&mut packet).unwrap(), ReadStatus::Ok);
+        assert_eq!(packet.data(), &[2]);
+        assert_eq!(
+            sr.next_packet(&mut packet).unwrap(),
+            ReadStatus::Lost {
+                bitstream_serial_number: 7,
+                missing_pages: 1
+            }
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "writer")]
+    fn test_push_reader_packets() {
+        let big_packet_1 = vec![0xAA; 100_000];
+        let big_packet_2 = vec![0xBB; 90_000];
+        let pages = multiplexed_pages(&big_packet_1, &big_packet_2);
+
+        // Prepend some garbage, so that the reader needs to sync first.
+        let mut d = vec![0x4F, 0x67, 0x00, 0x4F];
+        d.extend(write_pages(&[
+            &pages[0], &pages[1], &pages[2], &pages[4], &pages[3], &pages[5], &pages[6], &pages[7],
+        ]));
+
+        let mut pr = PushReader::new();
+        let mut packet = Packet::default();
+        let mut packets: Vec<(u32, Vec<u8>)> = Vec::new();
+
+        for chunk in d.chunks(1000) {
+            pr.push(chunk);
+            loop {
+                match pr.next_packet(&mut packet).unwrap() {
+                    ReadStatus::Ok => {
+                        packets.push((packet.bitstream_serial_number(), packet.data().to_vec()))
+                    }
+                    ReadStatus::NeedMoreData => break,
+                    status => panic!("unexpected status: {:?}", status),
+                }
+            }
+            assert_eq!(pr.buffered_bytes(), 0);
+        }
+
+        assert_eq!(
+            packets,
+            vec![
+                (1, vec![0x1]),
+                (2, vec![0x2]),
+                (1, big_packet_1),
+                (2, big_packet_2),
+                (1, vec![0x3]),
+                (2, vec![0x4]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_push_reader_page() {
+        let d: Vec<u8> = vec![
+            0x4F, 0x67, 0x67, 0x53, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x4A, 0xC9, 0x09, 0xB6, 0x00, 0x00, 0x00, 0x00, 0xF9, 0x20, 0x89, 0xF8, 0x01, 0x13,
+            0x4F, 0x70, 0x75, 0x73, 0x48, 0x65, 0x61, 0x64, 0x01, 0x02, 0x38, 0x01, 0x80, 0xBB,
+            0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+
+        let mut pr = PushReader::new();
+        let mut page = Page::default();
+
+        for byte in &d[..d.len() - 1] {
+            pr.push(&[*byte]);
+            let res = pr.next_page(&mut page).unwrap();
+            assert_eq!(res, ReadStatus::NeedMoreData);
+        }
+
+        pr.push(&d[d.len() - 1..]);
+        let res = pr.next_page(&mut page).unwrap();
+        assert_eq!(res, ReadStatus::Ok);
+        assert_eq!(page.bitstream_serial_number(), 0xB609C94A);
+        assert_eq!(page.data(), &d[28..]);
+    }
+
+    #[test]
+    fn test_push_reader_unable_to_sync() {
+        let mut pr = PushReader::new();
+        let mut packet = Packet::default();
+
+        pr.push(&vec![0_u8; MAX_PAGE_SIZE + 1]);
+        assert!(matches!(
+            pr.next_packet(&mut packet),
+            Err(ReadError::UnableToSync)
+        ));
+    }
+
+    /// Reader that returns the data in small chunks and fails with the given error kind before
+    /// every chunk.
+    struct NonBlockingReader {
+        data: Vec<u8>,
+        position: usize,
+        would_block: bool,
+        kind: ErrorKind,
+    }
+
+    impl Read for NonBlockingReader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.would_block = !self.would_block;
+            if self.would_block {
+                return Err(std::io::Error::new(self.kind, "would block"));
+            }
+
+            let size = usize::min(usize::min(buf.len(), 3), self.data.len() - self.position);
+            buf[..size].copy_from_slice(&self.data[self.position..self.position + size]);
+            self.position += size;
+
+            Ok(size)
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "writer")]
+    fn test_non_blocking_reader() {
+        let big_packet_1 = vec![0xAA; 100_000];
+        let big_packet_2 = vec![0xBB; 90_000];
+        let pages = multiplexed_pages(&big_packet_1, &big_packet_2);
+        let d = write_pages(&pages.iter().collect::<Vec<_>>());
+
+        let mut sr = StreamReader::new(NonBlockingReader {
+            data: d,
+            position: 0,
+            would_block: false,
+            kind: ErrorKind::WouldBlock,
+        });
+        let mut packet = Packet::default();
+        let mut packets: Vec<(u32, Vec<u8>)> = Vec::new();
+
+        loop {
+            match sr.next_packet(&mut packet).unwrap() {
+                ReadStatus::Ok => {
+                    packets.push((packet.bitstream_serial_number(), packet.data().to_vec()))
+                }
+                ReadStatus::NeedMoreData => continue,
+                ReadStatus::Eof => break,
+                status => panic!("unexpected status: {:?}", status),
+            }
+        }
+
+        assert_eq!(
+            packets,
+            vec![
+                (1, vec![0x1]),
+                (2, vec![0x2]),
+                (1, big_packet_1),
+                (2, big_packet_2),
+                (1, vec![0x3]),
+                (2, vec![0x4]),
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "writer")]
+    fn test_interrupted_reader() {
+        let big_packet_1 = vec![0xAA; 100_000];
+        let big_packet_2 = vec![0xBB; 90_000];
+        let pages = multiplexed_pages(&big_packet_1, &big_packet_2);
+        let d = write_pages(&pages.iter().collect::<Vec<_>>());
+
+        let mut sr = StreamReader::new(NonBlockingReader {
+            data: d,
+            position: 0,
+            would_block: false,
+            kind: ErrorKind::Interrupted,
+        });
+        let mut packet = Packet::default();
+        let mut packets = 0;
+
+        loop {
+            match sr.next_packet(&mut packet).unwrap() {
+                ReadStatus::Ok => packets += 1,
+                ReadStatus::Eof => break,
+                status => panic!("unexpected status: {:?}", status),
+            }
+        }
+
+        assert_eq!(packets, 6);
+    }
+
+    #[test]
+    fn test_truncated_page() {
+        let d: Vec<u8> = vec![
+            0x4F, 0x67, 0x67, 0x53, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x4A, 0xC9, 0x09, 0xB6, 0x00, 0x00, 0x00, 0x00, 0xF9, 0x20, 0x89, 0xF8, 0x01, 0x13,
+            0x4F, 0x70, 0x75, 0x73, 0x48, 0x65, 0x61, 0x64, 0x01, 0x02, 0x38, 0x01, 0x80, 0xBB,
+        ];
+        let c = Cursor::new(d);
+
+        let mut sr = StreamReader::new(c);
+        let mut packet = Packet::default();
+        assert!(matches!(
+            sr.next_packet(&mut packet),
+            Err(ReadError::TruncatedPage)
+        ));
+    }
+
+    #[test]
+    fn test_trailing_data() {
+        let d: Vec<u8> = vec![
+            0x4F, 0x67, 0x67, 0x53, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x4A, 0xC9, 0x09, 0xB6, 0x00, 0x00, 0x00, 0x00, 0xF9, 0x20, 0x89, 0xF8, 0x01, 0x13,
+            0x4F, 0x70, 0x75, 0x73, 0x48, 0x65, 0x61, 0x64, 0x01, 0x02, 0x38, 0x01, 0x80, 0xBB,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x4F, 0x67,
+        ];
+        let c = Cursor::new(d);
+
+        let mut sr = StreamReader::new(c);
+        let mut packet = Packet::default();
+        assert_eq!(sr.next_packet(&mut packet).unwrap(), ReadStatus::Ok);
+        assert_eq!(sr.next_packet(&mut packet).unwrap(), ReadStatus::Eof);
+    }
+
+    struct FailingReader;
+
+    impl Read for FailingReader {
+        fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::new(
+                ErrorKind::PermissionDenied,
+                "permission denied",
+            ))
+        }
+    }
+
+    #[test]
+    fn test_io_error() {
+        let mut sr = StreamReader::new(FailingReader);
+        let mut packet = Packet::default();
+        match sr.next_packet(&mut packet) {
+            Err(ReadError::IoError(err)) => assert_eq!(err.kind(), ErrorKind::PermissionDenied),
+            res => panic!("unexpected result: {:?}", res),
+        }
+    }
+
     // TODO write a test for reading packets (feeding data with the writer)
     // TODO write a test for seeking to 0
     // TODO write a test for seeking to u64::MAX
@@ -0,0 +1,183 @@
+//! Async bitstream reader.
+
+use std::future::poll_fn;
+use std::io::ErrorKind;
+use std::pin::Pin;
+
+use futures_io::AsyncRead;
+
+use super::{BitStreamReader, Packet, ReadStatus};
+use crate::{Page, ReadError};
+
+/// Generic OGG stream reader for async readers.
+///
+/// Tokio readers can be used by wrapping them into a `TokioCompat`
+/// (requires the "tokio" feature).
+#[derive(Clone, Debug)]
+pub struct AsyncStreamReader<R: AsyncRead + Unpin> {
+    inner: BitStreamReader,
+    reader: R,
+}
+
+impl<R: AsyncRead + Unpin> AsyncStreamReader<R> {
+    /// Creates a new `AsyncStreamReader`.
+    pub fn new(reader: R) -> Self {
+        Self {
+            inner: Default::default(),
+            reader,
+        }
+    }
+
+    /// Consumes the `AsyncStreamReader` and returns the reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Reads the next packet from the reader.
+    ///
+    /// Behaves like `StreamReader::next_packet()`. The returned future is cancel safe:
+    /// dropping it keeps the data read so far and the next call resumes at the same position.
+    pub async fn next_packet(&mut self, packet: &mut Packet) -> Result<ReadStatus, ReadError> {
+        packet.data.clear();
+
+        loop {
+            if self.inner.pop_packet(packet) {
+                return Ok(ReadStatus::Ok);
+            }
+
+            let page_size = match self.read_page().await {
+                Ok(page_size) => page_size,
+                Err(err) => {
+                    handle_eof!(err, return Ok(ReadStatus::Eof));
+                }
+            };
+
+            if let Some(status) = self.inner.queue_page(page_size)? {
+                return Ok(status);
+            }
+        }
+    }
+
+    /// Reads the next page from the reader.
+    ///
+    /// Behaves like `StreamReader::next_page()`. The returned future is cancel safe:
+    /// dropping it keeps the data read so far and the next call resumes at the same position.
+    pub async fn next_page(&mut self, page: &mut Page) -> Result<ReadStatus, ReadError> {
+        self.inner.discard_packets();
+
+        let page_size = match self.read_page().await {
+            Ok(page_size) => page_size,
+            Err(err) => {
+                handle_eof!(err, return Ok(ReadStatus::Eof));
+            }
+        };
+
+        self.inner.write_page(page_size, page)
+    }
+
+    /// Reads data from the reader into the page buffer until the page is complete and returns
+    /// its size.
+    async fn read_page(&mut self) -> Result<usize, ReadError> {
+        let Self { inner, reader } = self;
+
+        loop {
+            let read = match poll_fn(|cx| {
+                Pin::new(&mut *reader).poll_read(cx, inner.unfilled_page_buffer())
+            })
+            .await
+            {
+                Ok(0) => return Err(inner.eof_error()),
+                Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Err(inner.eof_error()),
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Ok(read) => read,
+                Err(err) => return Err(err.into()),
+            };
+
+            if let Some(page_size) = inner.advance_page_buffer(read)? {
+                return Ok(page_size);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::panic)]
+    #![allow(clippy::unwrap_used)]
+
+    use std::task::Poll;
+
+    use super::*;
+    use crate::test_util::block_on;
+
+    /// Reader that returns the data in small chunks and is pending before every chunk.
+    struct PendingReader {
+        data: Vec<u8>,
+        position: usize,
+        is_pending: bool,
+    }
+
+    impl AsyncRead for PendingReader {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut std::task::Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<std::io::Result<usize>> {
+            self.is_pending = !self.is_pending;
+            if self.is_pending {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+
+            let size = usize::min(usize::min(buf.len(), 3), self.data.len() - self.position);
+            let position = self.position;
+            buf[..size].copy_from_slice(&self.data[position..position + size]);
+            self.position += size;
+
+            Poll::Ready(Ok(size))
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "writer")]
+    fn test_next_packet() {
+        let big_packet = vec![0xAA; 100_000];
+
+        let mut sw = crate::StreamWriter::new(std::io::Cursor::new(vec![]));
+        sw.begin_logical_stream(1, &[0x1]).unwrap();
+        sw.push_packet(1, &big_packet, 10).unwrap();
+        sw.end_logical_stream(1, &[0x2], 20).unwrap();
+
+        let mut sr = AsyncStreamReader::new(PendingReader {
+            data: sw.into_inner().into_inner(),
+            position: 0,
+            is_pending: false,
+        });
+        let mut packet = Packet::default();
+
+        let expected: [&[u8]; 3] = [&[0x1], &big_packet, &[0x2]];
+        for data in expected.iter() {
+            let res = block_on(sr.next_packet(&mut packet)).unwrap();
+            assert_eq!(res, ReadStatus::Ok);
+            assert_eq!(packet.data(), *data);
+        }
+
+        let res = block_on(sr.next_packet(&mut packet)).unwrap();
+        assert_eq!(res, ReadStatus::Eof);
+    }
+
+    #[test]
+    fn test_truncated_page() {
+        let mut sr = AsyncStreamReader::new(PendingReader {
+            data: vec![0x4F, 0x67, 0x67, 0x53, 0x00, 0x02],
+            position: 0,
+            is_pending: false,
+        });
+        let mut page = Page::default();
+
+        assert!(matches!(
+            block_on(sr.next_page(&mut page)),
+            Err(ReadError::TruncatedPage)
+        ));
+    }
+}
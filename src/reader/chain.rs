@@ -0,0 +1,1306 @@
+//! Enumeration of the chains and logical bitstreams of a physical bitstream.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::io::{ErrorKind, Read, Seek, SeekFrom};
+use std::ops::Range;
+use std::time::Duration;
+
+use super::{BitStreamReader, FileReader, Packet, ReadStatus};
+use crate::granule::{fraction_to_time, granule_mapper};
+use crate::opus::packet_duration;
+use crate::{
+    parse_u32_le, parse_u64_le, Codec, Fisbone, GranuleMapper, ReadError, SkeletonIndex,
+    BITSTREAM_SERIAL_NUMBER_RANGE, BOS_VALUE, CONTINUATION_VALUE, GRANULE_POSITION_RANGE,
+    HEADER_TYPE_INDEX, PAGE_SEQUENCE_NUMBER_RANGE, SEGMENT_COUNT_INDEX, SEGMENT_TABLE_INDEX,
+};
+
+/// Size of the chunks that are searched when scanning backwards.
+const CHUNK_SIZE: u64 = 65_536;
+
+/// A chain of concurrently multiplexed logical bitstreams.
+///
+/// Chained files (like live stream recordings) contain multiple chains one after the other.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Chain {
+    /// The byte range of the chain inside the file.
+    byte_range: Range<u64>,
+    /// The logical bitstreams of the chain.
+    streams: Vec<StreamInfo>,
+    /// The fisbones of the Skeleton logical bitstream.
+    fisbones: Vec<Fisbone>,
+    /// The keyframe indexes of the Skeleton logical bitstream.
+    skeleton_indexes: Vec<SkeletonIndex>,
+}
+
+impl Chain {
+    /// The byte range of the chain inside the file.
+    pub fn byte_range(&self) -> Range<u64> {
+        self.byte_range.clone()
+    }
+
+    /// The logical bitstreams of the chain in the order of their BOS pages.
+    pub fn streams(&self) -> &[StreamInfo] {
+        self.streams.as_ref()
+    }
+
+    /// Returns the logical bitstream with the given serial number.
+    pub fn stream(&self, bitstream_serial_number: u32) -> Option<&StreamInfo> {
+        self.streams
+            .iter()
+            .find(|s| s.bitstream_serial_number == bitstream_serial_number)
+    }
+
+    /// The duration of the chain, which is the longest duration of its logical bitstreams.
+    ///
+    /// Is `None` if the duration of none of the logical bitstreams is known.
+    pub fn duration(&self) -> Option<Duration> {
+        self.streams.iter().filter_map(StreamInfo::duration).max()
+    }
+
+    /// Returns the fisbone of the logical bitstream with the given serial number.
+    pub fn fisbone(&self, bitstream_serial_number: u32) -> Option<&Fisbone> {
+        self.fisbones
+            .iter()
+            .find(|fisbone| fisbone.bitstream_serial_number() == bitstream_serial_number)
+    }
+
+    /// Returns the Skeleton index of the logical bitstream with the given serial number.
+    pub fn skeleton_index(&self, bitstream_serial_number: u32) -> Option<&SkeletonIndex> {
+        self.skeleton_indexes
+            .iter()
+            .find(|index| index.bitstream_serial_number() == bitstream_serial_number)
+    }
+
+    /// Returns the offset of the last keypoint at or before the given time inside the
+    /// file, if the logical bitstream has a Skeleton index.
+    pub(super) fn keypoint_offset(
+        &self,
+        bitstream_serial_number: u32,
+        time: Duration,
+    ) -> Option<u64> {
+        let keypoint = self
+            .skeleton_index(bitstream_serial_number)?
+            .keypoint_before(time)?;
+
+        self.byte_range
+            .start
+            .checked_add(keypoint.byte_offset())
+            .filter(|offset| *offset < self.byte_range.end)
+    }
+
+    /// Returns the offset of the last keypoint at or before the given granule position
+    /// inside the file. The granule position is mapped to time by the granule rate of the
+    /// fisbone of the logical bitstream.
+    pub(super) fn keypoint_offset_of_granule(
+        &self,
+        bitstream_serial_number: u32,
+        granule_position: u64,
+    ) -> Option<u64> {
+        let (numerator, denominator) = self
+            .fisbone(bitstream_serial_number)?
+            .granule_time(granule_position)?;
+        let time = fraction_to_time(
+            u128::try_from(numerator).ok()?,
+            u128::try_from(denominator).ok()?,
+        )?;
+
+        self.keypoint_offset(bitstream_serial_number, time)
+    }
+}
+
+/// Information about a logical bitstream inside a chain.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StreamInfo {
+    /// Unique serial ID of the logical bitstream.
+    bitstream_serial_number: u32,
+    /// The packet of the BOS page.
+    bos_packet: Vec<u8>,
+    /// The first granule position after the BOS page.
+    first_granule_position: Option<u64>,
+    /// The granule position at the start of the page with the first granule position.
+    start_granule_position: Option<u64>,
+    /// The last granule position of the logical bitstream.
+    last_granule_position: Option<u64>,
+}
+
+impl StreamInfo {
+    /// Unique serial ID of the logical bitstream.
+    pub fn bitstream_serial_number(&self) -> u32 {
+        self.bitstream_serial_number
+    }
+
+    /// The packet of the BOS page, which normally identifies the codec.
+    pub fn bos_packet(&self) -> &[u8] {
+        self.bos_packet.as_ref()
+    }
+
+    /// The codec of the logical bitstream, identified by its BOS packet.
+    pub fn codec(&self) -> Codec {
+        Codec::detect(&self.bos_packet)
+    }
+
+    /// The first granule position found on a page after the BOS page.
+    ///
+    /// Is `None` if the logical bitstream has no pages with a granule position after
+    /// its BOS page.
+    pub fn first_granule_position(&self) -> Option<u64> {
+        self.first_granule_position
+    }
+
+    /// The granule position at the start of the page with the first granule position.
+    ///
+    /// For Opus it's the first granule position minus the durations of the packets that
+    /// end on its page. Other codecs don't tell the durations of their packets, so the first
+    /// granule position is used as is and the packets of its page are not part of the
+    /// logical bitstream's duration.
+    pub fn start_granule_position(&self) -> Option<u64> {
+        self.start_granule_position
+    }
+
+    /// The last granule position of the logical bitstream.
+    ///
+    /// Is `None` if the logical bitstream has no pages with a granule position after
+    /// its BOS page.
+    pub fn last_granule_position(&self) -> Option<u64> {
+        self.last_granule_position
+    }
+
+    /// The difference between the last and the start granule position.
+    pub fn granule_duration(&self) -> Option<u64> {
+        self.last_granule_position?
+            .checked_sub(self.start_granule_position?)
+    }
+
+    /// The duration of the logical bitstream, measured from the start granule position.
+    ///
+    /// Is only known for codecs with a granule mapper.
+    pub fn duration(&self) -> Option<Duration> {
+        let mapper = self.granule_mapper()?;
+        // Opus granule positions in front of the pre-skip have no time, playback starts at
+        // zero.
+        let first = mapper
+            .granule_to_time(self.start_granule_position?)
+            .unwrap_or_default();
+        let last = mapper.granule_to_time(self.last_granule_position?)?;
+
+        last.checked_sub(first)
+    }
+
+    /// Returns the granule mapper of the codec, configured by the BOS packet.
+    pub fn granule_mapper(&self) -> Option<Box<dyn GranuleMapper>> {
+        granule_mapper(&self.bos_packet)
+    }
+}
+
+/// The header information of a page inside the file.
+#[derive(Clone, Debug)]
+pub(super) struct PageInfo {
+    pub(super) header_type: u8,
+    pub(super) granule_position: u64,
+    pub(super) bitstream_serial_number: u32,
+    pub(super) page_sequence_number: u32,
+    pub(super) start: u64,
+    pub(super) end: u64,
+}
+
+impl PageInfo {
+    pub(super) fn is_bos(&self) -> bool {
+        self.header_type & BOS_VALUE != 0
+    }
+}
+
+/// Returns the first candidate whose last position reaches the target, or the last candidate
+/// if there is none. The candidates are the chains of a logical bitstream with their last
+/// position, which is compared in the order of the target.
+pub(super) fn find_chain<T, P: Ord>(
+    candidates: impl IntoIterator<Item = (T, Option<P>)>,
+    target: &P,
+) -> Option<T> {
+    let mut found = None;
+    for (candidate, last_position) in candidates {
+        found = Some(candidate);
+        if last_position.map_or(false, |position| position >= *target) {
+            break;
+        }
+    }
+
+    found
+}
+
+/// The result of probing the pages during the search of a chain end.
+enum ChainProbe {
+    /// The pages belong to the chain, which continues after the given offset.
+    Chain(u64),
+    /// The chain ends at the given offset, where the BOS page of the next chain starts.
+    End(u64),
+    /// The pages belong to a following chain. Contains the start of the first page.
+    Later(u64),
+    /// There are no pages after the offset.
+    Eof,
+}
+
+impl<R: Read + Seek> FileReader<R> {
+    /// Returns the chains of the file and the logical bitstreams they contain.
+    ///
+    /// The end of each chain is searched by bisecting for the BOS page of the next chain. The
+    /// first granule positions are read from the start of the chain and the last ones by
+    /// scanning backwards from its end. Only pages with a valid checksum are used.
+    ///
+    /// The chains are enumerated by the first call and cached afterwards. If the file grew in
+    /// the meantime (like a live stream that is still recorded), its last chain is scanned
+    /// again together with the new chains. The position of the reader is not changed.
+    pub fn streams(&mut self) -> Result<&[Chain], ReadError> {
+        let position = self.reader.stream_position()?;
+        let file_end = self.reader.seek(SeekFrom::End(0))?;
+
+        let scanned = if self.chains.is_none() || file_end != self.file_end {
+            // A file that only grew keeps its chains, except for the last one which might
+            // have grown as well.
+            let offset = self
+                .chains
+                .as_ref()
+                .filter(|_| file_end > self.file_end)
+                .and_then(|chains| chains.last())
+                .map_or(0, |chain| chain.byte_range.start);
+
+            let mut scanner = BitStreamReader::default();
+            Some((
+                offset,
+                scanner.scan_chains(&mut self.reader, offset, file_end),
+            ))
+        } else {
+            None
+        };
+
+        self.reader.seek(SeekFrom::Start(position))?;
+
+        if let Some((offset, scanned)) = scanned {
+            let mut chains = self.chains.take().unwrap_or_default();
+            chains.retain(|chain| chain.byte_range.start < offset);
+            chains.extend(scanned?);
+
+            self.chains = Some(chains);
+            self.file_end = file_end;
+        }
+
+        Ok(self.chains.as_deref().unwrap_or_default())
+    }
+
+    /// Returns the duration of the file, which is the sum of the durations of its chains.
+    ///
+    /// Is `None` if the file has no chains or if the duration of a chain is not known.
+    pub fn duration(&mut self) -> Result<Option<Duration>, ReadError> {
+        let chains = self.streams()?;
+        if chains.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(chains.iter().map(Chain::duration).sum())
+    }
+}
+
+impl BitStreamReader {
+    /// Scans the file for the chains which start at or after the given offset.
+    pub(super) fn scan_chains<R: Read + Seek>(
+        &mut self,
+        reader: &mut R,
+        mut offset: u64,
+        file_end: u64,
+    ) -> Result<Vec<Chain>, ReadError> {
+        let mut chains = Vec::new();
+        while let Some(chain) = self.scan_chain(reader, offset, file_end)? {
+            offset = chain.byte_range.end;
+            chains.push(chain);
+        }
+
+        Ok(chains)
+    }
+
+    /// Scans the chain which starts with the first BOS page at or after the given offset.
+    fn scan_chain<R: Read + Seek>(
+        &mut self,
+        reader: &mut R,
+        offset: u64,
+        file_end: u64,
+    ) -> Result<Option<Chain>, ReadError> {
+        // Skip all pages in front of the first BOS page.
+        let mut next_page = self.probe_next_page(reader, offset)?;
+        while let Some(page) = next_page.as_ref().filter(|page| !page.is_bos()) {
+            next_page = self.probe_next_page(reader, page.end)?;
+        }
+
+        let chain_start = match next_page.as_ref() {
+            Some(page) => page.start,
+            None => return Ok(None),
+        };
+
+        // All BOS pages of a chain are in front of its other pages.
+        let mut streams: Vec<StreamInfo> = Vec::new();
+        let mut sequence_numbers = HashMap::new();
+        let mut headers_end = chain_start;
+        while let Some(page) = next_page {
+            if !page.is_bos()
+                || streams
+                    .iter()
+                    .any(|s| s.bitstream_serial_number == page.bitstream_serial_number)
+            {
+                headers_end = page.start;
+                break;
+            }
+
+            streams.push(StreamInfo {
+                bitstream_serial_number: page.bitstream_serial_number,
+                bos_packet: self.first_packet().to_vec(),
+                first_granule_position: None,
+                start_granule_position: None,
+                last_granule_position: None,
+            });
+            sequence_numbers.insert(page.bitstream_serial_number, page.page_sequence_number);
+
+            headers_end = page.end;
+            next_page = self.probe_next_page(reader, page.end)?;
+        }
+
+        let chain_end =
+            self.bisect_chain_end(reader, &mut sequence_numbers, headers_end, file_end)?;
+
+        self.scan_first_granule_positions(reader, &mut streams, headers_end, chain_end)?;
+        self.scan_last_granule_positions(reader, &mut streams, headers_end, chain_end)?;
+
+        let byte_range = chain_start..chain_end;
+        let (fisbones, skeleton_indexes) =
+            match streams.iter().find(|s| s.codec() == Codec::Skeleton) {
+                Some(skeleton) => {
+                    read_skeleton_headers(reader, skeleton.bitstream_serial_number, &byte_range)?
+                }
+                None => (Vec::new(), Vec::new()),
+            };
+
+        Ok(Some(Chain {
+            byte_range,
+            streams,
+            fisbones,
+            skeleton_indexes,
+        }))
+    }
+
+    /// Searches the end of the chain, which is the start of the next BOS page.
+    ///
+    /// The pages of a chain are contiguous, so the search gallops forward from the end of the
+    /// headers and bisects once it passed the end of the chain. The serial numbers of the
+    /// chain are mapped to the page sequence number of the last page found so far.
+    fn bisect_chain_end<R: Read + Seek>(
+        &mut self,
+        reader: &mut R,
+        sequence_numbers: &mut HashMap<u32, u32>,
+        headers_end: u64,
+        file_end: u64,
+    ) -> Result<u64, ReadError> {
+        let mut chain_end = file_end;
+        let mut left = headers_end;
+        let mut right = file_end;
+
+        while left < right {
+            // The probes are at most a quarter of the searched distance apart. A following
+            // chain that reuses a serial number therefore can't catch up with the page
+            // sequence numbers of the chain, unless its pages are a lot denser.
+            let step = (left - headers_end) / 4;
+            let mid = left + u64::min(step, (right - left) / 2);
+
+            match self.probe_chain_pages(reader, sequence_numbers, mid)? {
+                ChainProbe::Chain(end) => left = end,
+                ChainProbe::End(end) => {
+                    chain_end = end;
+                    left = end;
+                    right = end;
+                }
+                ChainProbe::Later(start) => {
+                    chain_end = u64::min(chain_end, start);
+                    right = mid;
+                }
+                ChainProbe::Eof => right = mid,
+            }
+        }
+
+        Ok(chain_end)
+    }
+
+    /// Reads the pages at or after the given offset until all logical bitstreams of the
+    /// chain were found and tells if they belong to the chain.
+    ///
+    /// A page belongs to the chain if it's not a BOS page, its logical bitstream is part
+    /// of the chain and its page sequence number is higher than the one in
+    /// `sequence_numbers`. A following chain that reuses a serial number starts a new page
+    /// sequence, so its pages are told apart even if the BOS page isn't part of the probe.
+    /// The sequence numbers are only updated if the pages belong to the chain.
+    fn probe_chain_pages<R: Read + Seek>(
+        &mut self,
+        reader: &mut R,
+        sequence_numbers: &mut HashMap<u32, u32>,
+        offset: u64,
+    ) -> Result<ChainProbe, ReadError> {
+        let mut found: HashMap<u32, u32> = HashMap::new();
+        let mut start = None;
+        let mut end = offset;
+
+        while found.len() < sequence_numbers.len() && end - offset < CHUNK_SIZE {
+            let page = match self.probe_next_page(reader, end)? {
+                Some(page) => page,
+                None => break,
+            };
+            let first_start = *start.get_or_insert(page.start);
+
+            // The next chain starts with a BOS page.
+            if page.is_bos() {
+                if page.start == first_start {
+                    return Ok(ChainProbe::Later(first_start));
+                }
+                sequence_numbers.extend(found);
+                return Ok(ChainProbe::End(page.start));
+            }
+
+            // Without a BOS page in between, all probed pages belong to the same chain.
+            match sequence_numbers.get(&page.bitstream_serial_number) {
+                Some(sequence_number) if page.page_sequence_number > *sequence_number => {}
+                _ => return Ok(ChainProbe::Later(first_start)),
+            }
+
+            found.insert(page.bitstream_serial_number, page.page_sequence_number);
+            end = page.end;
+        }
+
+        if start.is_none() {
+            return Ok(ChainProbe::Eof);
+        }
+
+        sequence_numbers.extend(found);
+        Ok(ChainProbe::Chain(end))
+    }
+
+    /// Reads the pages of the chain from the start until all logical bitstreams have a first
+    /// and a start granule position.
+    fn scan_first_granule_positions<R: Read + Seek>(
+        &mut self,
+        reader: &mut R,
+        streams: &mut [StreamInfo],
+        headers_end: u64,
+        chain_end: u64,
+    ) -> Result<(), ReadError> {
+        let mut missing = streams.len();
+        let mut offset = headers_end;
+
+        while missing != 0 {
+            let page = match self.probe_next_page(reader, offset)? {
+                Some(page) if page.end <= chain_end => page,
+                _ => break,
+            };
+
+            if page.granule_position != u64::MAX {
+                if let Some(stream) = streams.iter_mut().find(|s| {
+                    s.bitstream_serial_number == page.bitstream_serial_number
+                        && s.first_granule_position.is_none()
+                }) {
+                    stream.first_granule_position = Some(page.granule_position);
+                    stream.start_granule_position =
+                        Some(self.page_start_granule_position(stream.codec(), &page));
+                    missing -= 1;
+                }
+            }
+
+            offset = page.end;
+        }
+
+        Ok(())
+    }
+
+    /// Scans backwards from the end of the chain until all logical bitstreams have a last
+    /// granule position.
+    fn scan_last_granule_positions<R: Read + Seek>(
+        &mut self,
+        reader: &mut R,
+        streams: &mut [StreamInfo],
+        headers_end: u64,
+        chain_end: u64,
+    ) -> Result<(), ReadError> {
+        let mut missing = streams
+            .iter()
+            .filter(|s| s.first_granule_position.is_some())
+            .count();
+        let mut chunk_end = chain_end;
+
+        while missing != 0 && chunk_end > headers_end {
+            let chunk_start = u64::max(chunk_end.saturating_sub(CHUNK_SIZE), headers_end);
+
+            // The pages that start inside the chunk are read in order, so that
+            // the last page of a logical bitstream overwrites the ones before.
+            let mut found: Vec<(usize, u64)> = Vec::new();
+            let mut offset = chunk_start;
+            while let Some(page) = self.probe_next_page(reader, offset)? {
+                if page.start >= chunk_end || page.end > chain_end {
+                    break;
+                }
+
+                if page.granule_position != u64::MAX {
+                    if let Some(index) = streams.iter().position(|s| {
+                        s.bitstream_serial_number == page.bitstream_serial_number
+                            && s.last_granule_position.is_none()
+                    }) {
+                        found.retain(|(i, _)| *i != index);
+                        found.push((index, page.granule_position));
+                    }
+                }
+
+                offset = page.end;
+            }
+
+            for (index, granule_position) in found {
+                streams[index].last_granule_position = Some(granule_position);
+                missing -= 1;
+            }
+
+            chunk_end = chunk_start;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the last granule position of the logical bitstream on a page that ends
+    /// before the given offset. Pages before `start` are not searched, pages with a wrong
+    /// checksum are skipped.
+    pub(super) fn last_granule_position_before<R: Read + Seek>(
+        &mut self,
+        reader: &mut R,
+        bitstream_serial_number: u32,
+        start: u64,
+        offset: u64,
+    ) -> Result<Option<u64>, ReadError> {
+        let mut chunk_end = offset;
+
+        while chunk_end > start {
+            let chunk_start = u64::max(chunk_end.saturating_sub(CHUNK_SIZE), start);
+
+            let mut last_granule_position = None;
+            let mut page_offset = chunk_start;
+            while let Some(page) = self.probe_next_page(reader, page_offset)? {
+                if page.start >= chunk_end || page.end > offset {
+                    break;
+                }
+
+                if page.bitstream_serial_number == bitstream_serial_number
+                    && page.granule_position != u64::MAX
+                {
+                    last_granule_position = Some(page.granule_position);
+                }
+
+                page_offset = page.end;
+            }
+
+            if last_granule_position.is_some() {
+                return Ok(last_granule_position);
+            }
+
+            chunk_end = chunk_start;
+        }
+
+        Ok(None)
+    }
+
+    /// Reads the next valid page at or after the given offset into the page buffer.
+    ///
+    /// Returns `None` if there is no valid page until the EOF. A reader that would block
+    /// returns a `WouldBlock` I/O error, since the probed pages must be read completely.
+    pub(super) fn probe_next_page<R: Read + Seek>(
+        &mut self,
+        reader: &mut R,
+        offset: u64,
+    ) -> Result<Option<PageInfo>, ReadError> {
+        reader.seek(SeekFrom::Start(offset))?;
+        self.page_head = 0;
+        self.skipped_bytes = 0;
+
+        loop {
+            let page_size = match self.read_page(reader) {
+                Ok(Some(page_size)) => page_size,
+                Ok(None) => return Err(std::io::Error::from(ErrorKind::WouldBlock).into()),
+                Err(ReadError::UnableToSync) => continue,
+                Err(ReadError::TruncatedPage) => return Ok(None),
+                Err(ReadError::IoError(err)) if err.kind() == ErrorKind::UnexpectedEof => {
+                    return Ok(None)
+                }
+                Err(err) => return Err(err),
+            };
+
+            // Pages with wrong checksums are skipped.
+            if !self.verify_page(page_size)? {
+                continue;
+            }
+
+            let end = reader.stream_position()?;
+
+            return Ok(Some(PageInfo {
+                header_type: self.page_buffer[HEADER_TYPE_INDEX],
+                granule_position: parse_u64_le(&self.page_buffer[GRANULE_POSITION_RANGE]),
+                bitstream_serial_number: parse_u32_le(
+                    &self.page_buffer[BITSTREAM_SERIAL_NUMBER_RANGE],
+                ),
+                page_sequence_number: parse_u32_le(&self.page_buffer[PAGE_SEQUENCE_NUMBER_RANGE]),
+                start: end - u64::try_from(page_size)?,
+                end,
+            }));
+        }
+    }
+
+    /// Returns the granule position at the start of the page inside the page buffer.
+    ///
+    /// Opus packets tell their duration, which is subtracted for the packets that end on
+    /// the page. Other codecs use the granule position of the page.
+    fn page_start_granule_position(&self, codec: Codec, page: &PageInfo) -> u64 {
+        if codec != Codec::Opus {
+            return page.granule_position;
+        }
+
+        let duration: u64 = self
+            .page_packets(page)
+            .into_iter()
+            .filter_map(packet_duration)
+            .map(u64::from)
+            .sum();
+
+        page.granule_position.saturating_sub(duration)
+    }
+
+    /// Returns the packets of the page inside the page buffer that start and end on it.
+    fn page_packets(&self, page: &PageInfo) -> Vec<&[u8]> {
+        let table_end = SEGMENT_TABLE_INDEX + usize::from(self.page_buffer[SEGMENT_COUNT_INDEX]);
+
+        let mut packets = Vec::new();
+        let mut continued = page.header_type & CONTINUATION_VALUE != 0;
+        let mut packet_start = table_end;
+        let mut packet_end = table_end;
+        for lace in self.page_buffer[SEGMENT_TABLE_INDEX..table_end].iter() {
+            packet_end += usize::from(*lace);
+            if *lace != 255 {
+                // The start of a continued packet is on the page before.
+                if !continued {
+                    packets.push(&self.page_buffer[packet_start..packet_end]);
+                }
+                continued = false;
+                packet_start = packet_end;
+            }
+        }
+
+        packets
+    }
+
+    /// Returns the first packet of the page inside the page buffer.
+    fn first_packet(&self) -> &[u8] {
+        let table_end = SEGMENT_TABLE_INDEX + usize::from(self.page_buffer[SEGMENT_COUNT_INDEX]);
+
+        let mut packet_size = 0;
+        for lace in self.page_buffer[SEGMENT_TABLE_INDEX..table_end].iter() {
+            packet_size += usize::from(*lace);
+            if *lace != 255 {
+                break;
+            }
+        }
+
+        &self.page_buffer[table_end..table_end + packet_size]
+    }
+}
+
+/// Reads the fisbone and index packets of the Skeleton logical bitstream, which are part of
+/// its header packets in front of its EOS page.
+fn read_skeleton_headers<R: Read + Seek>(
+    reader: &mut R,
+    bitstream_serial_number: u32,
+    byte_range: &Range<u64>,
+) -> Result<(Vec<Fisbone>, Vec<SkeletonIndex>), ReadError> {
+    reader.seek(SeekFrom::Start(byte_range.start))?;
+
+    let mut packets = BitStreamReader::default();
+    let mut packet = Packet::default();
+    let mut fisbones = Vec::new();
+    let mut indexes = Vec::new();
+
+    while reader.stream_position()? < byte_range.end {
+        match packets.next_packet(reader, &mut packet) {
+            Ok(ReadStatus::Ok) => {}
+            Ok(ReadStatus::Eof) | Err(ReadError::TruncatedPage) => break,
+            Ok(_) => continue,
+            Err(err) => return Err(err),
+        }
+
+        // The index packets are header packets, which are in front of all data pages.
+        if packet.granule_position() != 0 {
+            break;
+        }
+
+        if packet.bitstream_serial_number() != bitstream_serial_number {
+            continue;
+        }
+
+        if let Some(index) = SkeletonIndex::parse(packet.data()) {
+            indexes.push(index);
+        } else if let Some(fisbone) = Fisbone::parse(packet.data()) {
+            fisbones.push(fisbone);
+        }
+
+        if packet.is_eos() {
+            break;
+        }
+    }
+
+    Ok((fisbones, indexes))
+}
+
+#[cfg(all(test, feature = "writer"))]
+mod tests {
+    #![allow(clippy::panic)]
+    #![allow(clippy::unwrap_used)]
+
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::{Packet, ReadStatus, StreamWriter};
+
+    /// OpusHead packet with a pre-skip of 312 samples.
+    const OPUS_HEAD: &[u8] = &[
+        0x4F, 0x70, 0x75, 0x73, 0x48, 0x65, 0x61, 0x64, 0x01, 0x02, 0x38, 0x01, 0x80, 0xBB, 0x00,
+        0x00, 0x00, 0x00, 0x00,
+    ];
+
+    /// Writes a chain with two logical bitstreams, each with the given amount of data pages.
+    fn write_chain(sw: &mut StreamWriter<Cursor<Vec<u8>>>, serials: [u32; 2], pages: u64) {
+        sw.begin_logical_stream(serials[0], b"first").unwrap();
+        sw.begin_logical_stream(serials[1], b"second").unwrap();
+
+        for i in 1..=pages {
+            for serial in serials.iter() {
+                sw.push_packet(*serial, &[0xAA; 4000], 100 + i * 10)
+                    .unwrap();
+                sw.flush(*serial).unwrap();
+            }
+        }
+
+        for serial in serials.iter() {
+            sw.end_logical_stream(*serial, &[0xBB; 10], 100 + pages * 10 + 5)
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_streams() {
+        let mut sw = StreamWriter::new(Cursor::new(vec![]));
+        write_chain(&mut sw, [1, 2], 100);
+        let first_chain_end = u64::try_from(sw.into_inner().get_ref().len()).unwrap();
+
+        let mut sw = StreamWriter::new(Cursor::new(vec![]));
+        write_chain(&mut sw, [1, 2], 100);
+        write_chain(&mut sw, [3, 4], 50);
+        let d = sw.into_inner().into_inner();
+        let file_end = u64::try_from(d.len()).unwrap();
+
+        let mut fr = FileReader::new(Cursor::new(d));
+
+        // Enumerating the streams doesn't change the position of the reader.
+        let mut packet = Packet::default();
+        assert_eq!(fr.next_packet(&mut packet).unwrap(), ReadStatus::Ok);
+
+        let chains = fr.streams().unwrap();
+        assert_eq!(chains.len(), 2);
+
+        assert_eq!(chains[0].byte_range(), 0..first_chain_end);
+        assert_eq!(chains[1].byte_range(), first_chain_end..file_end);
+
+        let stream = chains[0].stream(2).unwrap();
+        assert_eq!(stream.bos_packet(), b"second");
+        assert_eq!(stream.codec(), Codec::Unknown);
+        assert_eq!(stream.first_granule_position(), Some(110));
+        assert_eq!(stream.last_granule_position(), Some(1105));
+
+        let serials: Vec<u32> = chains[1]
+            .streams()
+            .iter()
+            .map(|s| s.bitstream_serial_number())
+            .collect();
+        assert_eq!(serials, vec![3, 4]);
+
+        let stream = chains[1].stream(3).unwrap();
+        assert_eq!(stream.bos_packet(), b"first");
+        assert_eq!(stream.first_granule_position(), Some(110));
+        assert_eq!(stream.last_granule_position(), Some(605));
+
+        assert_eq!(fr.next_packet(&mut packet).unwrap(), ReadStatus::Ok);
+        assert_eq!(packet.bitstream_serial_number(), 2);
+        assert_eq!(packet.data(), b"second");
+    }
+
+    #[test]
+    fn test_streams_reused_serial_numbers() {
+        let mut sw = StreamWriter::new(Cursor::new(vec![]));
+        write_chain(&mut sw, [1, 2], 100);
+        let first_chain_end = u64::try_from(sw.into_inner().get_ref().len()).unwrap();
+
+        let mut sw = StreamWriter::new(Cursor::new(vec![]));
+        write_chain(&mut sw, [1, 2], 100);
+        write_chain(&mut sw, [1, 2], 50);
+        let d = sw.into_inner().into_inner();
+        let file_end = u64::try_from(d.len()).unwrap();
+
+        // The pages of the second chain don't count to the first one.
+        let mut fr = FileReader::new(Cursor::new(d));
+        let chains = fr.streams().unwrap();
+        assert_eq!(chains.len(), 2);
+        assert_eq!(chains[0].byte_range(), 0..first_chain_end);
+        assert_eq!(chains[1].byte_range(), first_chain_end..file_end);
+
+        let stream = chains[0].stream(1).unwrap();
+        assert_eq!(stream.last_granule_position(), Some(1105));
+        let stream = chains[1].stream(1).unwrap();
+        assert_eq!(stream.first_granule_position(), Some(110));
+        assert_eq!(stream.last_granule_position(), Some(605));
+    }
+
+    #[test]
+    fn test_streams_empty_file() {
+        let mut fr = FileReader::new(Cursor::new(vec![]));
+        assert!(fr.streams().unwrap().is_empty());
+        assert_eq!(fr.duration().unwrap(), None);
+    }
+
+    /// Reader that would block on every read.
+    struct BlockingReader(Cursor<Vec<u8>>);
+
+    impl Read for BlockingReader {
+        fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+            Err(ErrorKind::WouldBlock.into())
+        }
+    }
+
+    impl Seek for BlockingReader {
+        fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+            self.0.seek(pos)
+        }
+    }
+
+    #[test]
+    fn test_streams_would_block() {
+        let mut sw = StreamWriter::new(Cursor::new(vec![]));
+        write_chain(&mut sw, [1, 2], 10);
+
+        let mut fr = FileReader::new(BlockingReader(sw.into_inner()));
+        match fr.streams() {
+            Err(ReadError::IoError(err)) => assert_eq!(err.kind(), ErrorKind::WouldBlock),
+            res => panic!("unexpected result: {:?}", res),
+        }
+    }
+
+    #[test]
+    fn test_duration() {
+        let mut sw = StreamWriter::new(Cursor::new(vec![]));
+
+        // The TOC byte of the packets tells a duration of 20 ms. The last granule position
+        // includes the pre-skip.
+        sw.begin_logical_stream(1, OPUS_HEAD).unwrap();
+        for i in 1..=50 {
+            sw.push_packet(1, &[0x08; 4000], 960 * i).unwrap();
+            sw.flush(1).unwrap();
+        }
+        sw.end_logical_stream(1, &[0x08], 960 * 100 + 312).unwrap();
+
+        // The serial number is reused by the second chain.
+        sw.begin_logical_stream(1, OPUS_HEAD).unwrap();
+        for i in 1..=50 {
+            sw.push_packet(1, &[0x08; 4000], 480_000 + 960 * i).unwrap();
+            sw.flush(1).unwrap();
+        }
+        sw.end_logical_stream(1, &[0x08], 480_000 + 48_000 * 10)
+            .unwrap();
+
+        let mut fr = FileReader::new(sw.into_inner());
+        let chains = fr.streams().unwrap();
+        assert_eq!(chains.len(), 2);
+
+        let stream = &chains[0].streams()[0];
+        assert_eq!(stream.first_granule_position(), Some(960));
+        assert_eq!(stream.start_granule_position(), Some(0));
+        assert_eq!(stream.granule_duration(), Some(960 * 100 + 312));
+        assert_eq!(chains[0].duration(), Some(Duration::from_millis(2000)));
+        assert_eq!(
+            chains[1].streams()[0].start_granule_position(),
+            Some(480_000)
+        );
+        assert_eq!(chains[1].duration(), Some(Duration::from_secs(10)));
+
+        assert_eq!(
+            fr.duration().unwrap(),
+            Some(Duration::from_millis(2000 + 10_000))
+        );
+    }
+
+    #[test]
+    fn test_duration_unknown_codec() {
+        let mut sw = StreamWriter::new(Cursor::new(vec![]));
+        write_chain(&mut sw, [1, 2], 10);
+
+        let mut fr = FileReader::new(sw.into_inner());
+        assert_eq!(fr.duration().unwrap(), None);
+    }
+
+    /// Writes a chain with a single logical bitstream. The packets contain their own
+    /// granule position.
+    fn write_granule_chain(sw: &mut StreamWriter<Cursor<Vec<u8>>>, granule_positions: &[u64]) {
+        sw.begin_logical_stream(1, OPUS_HEAD).unwrap();
+        for granule_position in granule_positions.iter() {
+            let mut data = granule_position.to_le_bytes().to_vec();
+            data.resize(3000, 0xAA);
+            sw.push_packet(1, &data, *granule_position).unwrap();
+            sw.flush(1).unwrap();
+        }
+        let last_granule_position = granule_positions.last().copied().unwrap_or_default();
+        sw.end_logical_stream(1, &[0xBB], last_granule_position + 500)
+            .unwrap();
+    }
+
+    /// Reads packets until one with data is found and returns its granule position.
+    fn next_granule_position(fr: &mut FileReader<Cursor<Vec<u8>>>) -> u64 {
+        let mut packet = Packet::default();
+        assert_eq!(fr.next_packet(&mut packet).unwrap(), ReadStatus::Ok);
+        crate::parse_u64_le(packet.data())
+    }
+
+    /// Returns the end of the last chain.
+    fn chains_end(fr: &mut FileReader<Cursor<Vec<u8>>>) -> u64 {
+        fr.streams().unwrap().last().unwrap().byte_range().end
+    }
+
+    #[test]
+    fn test_seek_chained() {
+        let first: Vec<u64> = (1..=100).map(|i| i * 1000).collect();
+        let second: Vec<u64> = (1..=100).map(|i| 200_000 + i * 1000).collect();
+
+        let mut sw = StreamWriter::new(Cursor::new(vec![]));
+        write_granule_chain(&mut sw, &first);
+        write_granule_chain(&mut sw, &second);
+        let mut fr = FileReader::new(sw.into_inner());
+
+        let result = fr.seek(1, 50_000).unwrap();
+        assert_eq!(result.granule_position(), Some(50_000));
+        assert_eq!(result.page_sequence_number(), Some(50));
+        assert!(!result.is_clamped());
+        assert_eq!(fr.reader.stream_position().unwrap(), result.byte_offset());
+        assert_eq!(next_granule_position(&mut fr), 50_000);
+
+        fr.seek(1, 250_500).unwrap();
+        assert_eq!(next_granule_position(&mut fr), 251_000);
+
+        fr.seek(1, 201_000).unwrap();
+        assert_eq!(next_granule_position(&mut fr), 201_000);
+
+        let result = fr.seek(1, 400_000).unwrap();
+        assert!(result.is_clamped());
+        assert_eq!(result.granule_position(), Some(300_500));
+        assert_eq!(result.page_sequence_number(), Some(101));
+
+        let result = fr.seek(1, u64::MAX).unwrap();
+        assert!(result.is_clamped());
+        assert_eq!(result.granule_position(), None);
+        assert_eq!(result.byte_offset(), chains_end(&mut fr));
+
+        assert!(matches!(
+            fr.seek(2, 1000),
+            Err(ReadError::UnknownBitstreamSerialNumber)
+        ));
+    }
+
+    #[test]
+    fn test_seek_to_time() {
+        let granule_positions: Vec<u64> = (1..=100).map(|i| i * 1000).collect();
+
+        let mut sw = StreamWriter::new(Cursor::new(vec![]));
+        write_granule_chain(&mut sw, &granule_positions);
+        let mut fr = FileReader::new(sw.into_inner());
+
+        // 1 second equals the granule position 48_312 because of the pre-skip.
+        fr.seek_to_time(1, Duration::from_secs(1)).unwrap();
+        assert_eq!(next_granule_position(&mut fr), 49_000);
+
+        let mapper = crate::SampleRateMapper::new(1000).unwrap();
+        fr.seek_to_time_with(1, Duration::from_secs(10), &mapper)
+            .unwrap();
+        assert_eq!(next_granule_position(&mut fr), 10_000);
+    }
+
+    /// Returns the Theora granule position of the frame with keyframes every 10 frames.
+    fn theora_granule_position(frame: u64) -> u64 {
+        ((frame / 10 * 10) << 6) | (frame % 10)
+    }
+
+    /// Writes a Theora logical bitstream. Pages contain the frames 0, 1-2, 3-4, ...
+    fn write_theora_stream() -> Vec<u8> {
+        let mut sw = StreamWriter::new(Cursor::new(vec![]));
+        sw.begin_logical_stream(1, b"\x80theora").unwrap();
+        for frame in 0..=60_u64 {
+            let mut data = frame.to_le_bytes().to_vec();
+            data.resize(1000, 0xAA);
+            sw.push_packet(1, &data, theora_granule_position(frame))
+                .unwrap();
+            if frame % 2 == 0 {
+                sw.flush(1).unwrap();
+            }
+        }
+        sw.end_logical_stream(1, &[0xBB], theora_granule_position(61))
+            .unwrap();
+        sw.into_inner().into_inner()
+    }
+
+    #[test]
+    fn test_seek_keyframe() {
+        let mapper = crate::TheoraMapper::new(6, 25, 1).unwrap();
+        let mut fr = FileReader::new(Cursor::new(write_theora_stream()));
+
+        // The page of frame 25 has the keyframe 20.
+        fr.seek_keyframe(1, 25 << 6, &mapper).unwrap();
+        assert_eq!(next_granule_position(&mut fr), 19);
+        assert_eq!(next_granule_position(&mut fr), 20);
+
+        // The page of frame 19 has the keyframe 20, so the one of the page before is used.
+        fr.seek_keyframe(1, 19 << 6, &mapper).unwrap();
+        assert_eq!(next_granule_position(&mut fr), 9);
+        assert_eq!(next_granule_position(&mut fr), 10);
+
+        fr.seek_to_time_with(1, Duration::from_millis(1500), &mapper)
+            .unwrap();
+        assert_eq!(next_granule_position(&mut fr), 29);
+        assert_eq!(next_granule_position(&mut fr), 30);
+    }
+
+    #[test]
+    fn test_seek_keyframe_corrupt_page() {
+        let mapper = crate::TheoraMapper::new(6, 25, 1).unwrap();
+
+        // The corrupt page of the frames 17-18 claims to depend on the keyframe 0.
+        let mut d = write_theora_stream();
+        let offset = usize::try_from(page_offset(&d, theora_granule_position(18))).unwrap();
+        d[offset + 6..offset + 14].copy_from_slice(&18_u64.to_le_bytes());
+        let mut fr = FileReader::new(Cursor::new(d));
+
+        // The keyframe is taken from the page of the frames 15-16 instead.
+        fr.seek_keyframe(1, 19 << 6, &mapper).unwrap();
+        assert_eq!(next_granule_position(&mut fr), 9);
+        assert_eq!(next_granule_position(&mut fr), 10);
+    }
+
+    #[test]
+    fn test_seek_with_pre_roll() {
+        let granule_positions: Vec<u64> = (1..=100).map(|i| i * 1000).collect();
+
+        let mut sw = StreamWriter::new(Cursor::new(vec![]));
+        write_granule_chain(&mut sw, &granule_positions);
+        let mut fr = FileReader::new(sw.into_inner());
+
+        let landed = fr.seek_with_pre_roll(1, 50_000, 3840).unwrap();
+        assert_eq!(landed.granule_position(), Some(47_000));
+        assert_eq!(next_granule_position(&mut fr), 47_000);
+
+        // 920 ms equal the granule position 44_472 because of the pre-skip.
+        let landed = fr
+            .seek_to_time_with_pre_roll(1, Duration::from_secs(1), Duration::from_millis(80))
+            .unwrap();
+        assert_eq!(landed.granule_position(), Some(45_000));
+        assert_eq!(next_granule_position(&mut fr), 45_000);
+
+        let landed = fr.seek_with_pre_roll(1, 2000, 3840).unwrap();
+        assert_eq!(landed.granule_position(), Some(0));
+    }
+
+    #[test]
+    fn test_seek_to_time_restarted_granule_positions() {
+        let granule_positions: Vec<u64> = (1..=100).map(|i| i * 1000).collect();
+
+        let mut sw = StreamWriter::new(Cursor::new(vec![]));
+        write_granule_chain(&mut sw, &granule_positions);
+        write_granule_chain(&mut sw, &granule_positions);
+        let mut fr = FileReader::new(sw.into_inner());
+        let second_chain_start = fr.streams().unwrap()[1].byte_range().start;
+
+        fr.seek_to_time(1, Duration::from_secs(1)).unwrap();
+        assert!(fr.reader.stream_position().unwrap() < second_chain_start);
+        assert_eq!(next_granule_position(&mut fr), 49_000);
+
+        // The second chain starts at the end of the first one, which has the granule position
+        // 100_500. 3 seconds equal the granule position 44_124 of the second chain.
+        let result = fr.seek_to_time(1, Duration::from_secs(3)).unwrap();
+        assert!(result.byte_offset() >= second_chain_start);
+        assert_eq!(result.granule_position(), Some(45_000));
+        assert_eq!(next_granule_position(&mut fr), 45_000);
+
+        let result = fr.seek_to_time(1, Duration::from_secs(10)).unwrap();
+        assert!(result.is_clamped());
+        assert!(result.byte_offset() >= second_chain_start);
+    }
+
+    #[test]
+    fn test_seek_without_bos_page() {
+        let granule_positions: Vec<u64> = (1..=100).map(|i| i * 1000).collect();
+
+        let mut sw = StreamWriter::new(Cursor::new(vec![]));
+        write_granule_chain(&mut sw, &granule_positions);
+        let d = sw.into_inner().into_inner();
+
+        // The recording starts in the middle of the logical bitstream.
+        let start = usize::try_from(page_offset(&d, 20_000)).unwrap();
+        let mut fr = FileReader::new(Cursor::new(d[start..].to_vec()));
+        assert!(fr.streams().unwrap().is_empty());
+
+        let result = fr.seek(1, 50_000).unwrap();
+        assert_eq!(result.granule_position(), Some(50_000));
+        assert_eq!(next_granule_position(&mut fr), 50_000);
+
+        fr.seek(1, 0).unwrap();
+        assert_eq!(next_granule_position(&mut fr), 20_000);
+
+        let result = fr.seek(1, 200_000).unwrap();
+        assert_eq!(result.granule_position(), Some(100_500));
+
+        assert!(matches!(
+            fr.seek(2, 1000),
+            Err(ReadError::UnknownBitstreamSerialNumber)
+        ));
+    }
+
+    #[test]
+    fn test_streams_growing_file() {
+        let granule_positions: Vec<u64> = (1..=100).map(|i| i * 1000).collect();
+
+        let mut sw = StreamWriter::new(Cursor::new(vec![]));
+        write_granule_chain(&mut sw, &granule_positions);
+        let first_chain_end = u64::try_from(sw.into_inner().get_ref().len()).unwrap();
+
+        let mut sw = StreamWriter::new(Cursor::new(vec![]));
+        write_granule_chain(&mut sw, &granule_positions);
+        write_granule_chain(&mut sw, &granule_positions);
+        let d = sw.into_inner().into_inner();
+
+        let recorded = usize::try_from(page_offset(&d, 50_000)).unwrap();
+        let mut fr = FileReader::new(Cursor::new(d[..recorded].to_vec()));
+        let chains = fr.streams().unwrap();
+        assert_eq!(chains.len(), 1);
+        assert_eq!(
+            chains[0].stream(1).unwrap().last_granule_position(),
+            Some(49_000)
+        );
+
+        // The last chain is scanned again once the file grew.
+        fr.reader.get_mut().extend_from_slice(&d[recorded..]);
+        let chains = fr.streams().unwrap();
+        assert_eq!(chains.len(), 2);
+        assert_eq!(chains[0].byte_range(), 0..first_chain_end);
+        assert_eq!(
+            chains[0].stream(1).unwrap().last_granule_position(),
+            Some(100_500)
+        );
+        assert_eq!(
+            chains[1].stream(1).unwrap().last_granule_position(),
+            Some(100_500)
+        );
+    }
+
+    /// Returns an index packet with a single keypoint at 500 ms for the logical bitstream 1.
+    /// The byte offset needs to be encoded in three bytes.
+    fn skeleton_index(byte_offset: u64) -> Vec<u8> {
+        let mut packet = b"index\0".to_vec();
+        packet.extend_from_slice(&1_u32.to_le_bytes());
+        packet.extend_from_slice(&1_u64.to_le_bytes());
+        packet.extend_from_slice(&1000_i64.to_le_bytes());
+        packet.extend_from_slice(&0_i64.to_le_bytes());
+        packet.extend_from_slice(&2000_i64.to_le_bytes());
+        packet.push(u8::try_from(byte_offset & 0x7F).unwrap());
+        packet.push(u8::try_from(byte_offset >> 7 & 0x7F).unwrap());
+        packet.push(u8::try_from(byte_offset >> 14).unwrap() | 0x80);
+        packet.extend_from_slice(&[0x74, 0x83]);
+        packet
+    }
+
+    /// Returns the offset of the page with the given granule position.
+    fn page_offset(data: &[u8], granule_position: u64) -> u64 {
+        let offset = (0..data.len() - 14)
+            .find(|i| {
+                data[*i..].starts_with(&crate::PAGER_MARKER)
+                    && parse_u64_le(&data[i + 6..]) == granule_position
+            })
+            .unwrap();
+        u64::try_from(offset).unwrap()
+    }
+
+    /// Writes a chain with a Skeleton index for the logical bitstream 1, which has the
+    /// granule positions of the pages 1000, 2000, ... The fisbone of the logical bitstream
+    /// is optional.
+    fn write_skeleton_chain(keypoint_offset: u64, fisbone: bool) -> Vec<u8> {
+        let mut fishead = b"fishead\0\x04\0\0\0".to_vec();
+        fishead.resize(80, 0);
+
+        let mut sw = StreamWriter::new(Cursor::new(vec![]));
+        sw.begin_logical_stream(10, &fishead).unwrap();
+        sw.begin_logical_stream(1, OPUS_HEAD).unwrap();
+        if fisbone {
+            let fisbone = Fisbone::new(1, "audio/opus", (48_000, 1), 3840);
+            sw.push_packet(10, &fisbone.to_packet(), 0).unwrap();
+        }
+        sw.push_packet(10, &skeleton_index(keypoint_offset), 0)
+            .unwrap();
+        sw.end_logical_stream(10, &[], 0).unwrap();
+        for i in 1..=100 {
+            let granule_position = i * 1000_u64;
+            let mut data = granule_position.to_le_bytes().to_vec();
+            data.resize(3000, 0xAA);
+            sw.push_packet(1, &data, granule_position).unwrap();
+            sw.flush(1).unwrap();
+        }
+        sw.end_logical_stream(1, &[0xBB], 100_500).unwrap();
+        sw.into_inner().into_inner()
+    }
+
+    #[test]
+    fn test_seek_skeleton_index() {
+        // All offsets of three bytes result in the same layout.
+        let keypoint_offset = page_offset(&write_skeleton_chain(1 << 14, false), 24_000);
+        let mut fr = FileReader::new(Cursor::new(write_skeleton_chain(keypoint_offset, false)));
+
+        let chains = fr.streams().unwrap();
+        let index = chains[0].skeleton_index(1).unwrap();
+        assert_eq!(index.keypoints()[0].byte_offset(), keypoint_offset);
+        assert!(chains[0].skeleton_index(10).is_none());
+
+        // The keypoint at 500 ms is used instead of searching the page of 1 second.
+        let result = fr.seek_to_time(1, Duration::from_secs(1)).unwrap();
+        assert_eq!(result.byte_offset(), keypoint_offset);
+        assert_eq!(result.granule_position(), Some(24_000));
+        assert_eq!(next_granule_position(&mut fr), 24_000);
+
+        // Targets in front of the first keypoint are searched.
+        fr.seek_to_time(1, Duration::from_millis(100)).unwrap();
+        assert_eq!(next_granule_position(&mut fr), 6000);
+    }
+
+    #[test]
+    fn test_seek_skeleton_index_granule_position() {
+        // The keypoint at 500 ms points to the page with the granule position 36_000.
+        let keypoint_offset = page_offset(&write_skeleton_chain(1 << 14, true), 36_000);
+        let mut fr = FileReader::new(Cursor::new(write_skeleton_chain(keypoint_offset, true)));
+        assert_eq!(
+            fr.streams().unwrap()[0].fisbone(1).unwrap().granule_rate(),
+            (48_000, 1)
+        );
+
+        // 30_000 granules equal 625 ms, so the search starts at the keypoint.
+        let result = fr.seek(1, 30_000).unwrap();
+        assert_eq!(result.granule_position(), Some(36_000));
+
+        // Targets in front of the first keypoint are searched in the whole chain.
+        let result = fr.seek(1, 12_000).unwrap();
+        assert_eq!(result.granule_position(), Some(12_000));
+        assert_eq!(next_granule_position(&mut fr), 12_000);
+    }
+}
@@ -0,0 +1,406 @@
+//! Ogg Opus logical bitstream reader.
+
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::io::{Read, Seek};
+
+use super::{FileReader, Packet, ReadStatus};
+use crate::opus::packet_duration;
+use crate::{OpusHead, OpusTags, ReadError};
+
+/// An audio packet of an Ogg Opus logical bitstream.
+///
+/// Positions are granule positions, counted in samples at 48 kHz including the pre-skip.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct OpusPacket {
+    data: Vec<u8>,
+    start_position: u64,
+    end_position: u64,
+    discard_start: u32,
+    discard_end: u32,
+}
+
+impl OpusPacket {
+    /// The payload of the packet.
+    pub fn data(&self) -> &[u8] {
+        self.data.as_ref()
+    }
+
+    /// The granule position of the first sample of the packet.
+    pub fn start_position(&self) -> u64 {
+        self.start_position
+    }
+
+    /// The granule position after the last sample of the packet.
+    pub fn end_position(&self) -> u64 {
+        self.end_position
+    }
+
+    /// The duration of the packet in samples at 48 kHz.
+    pub fn duration(&self) -> u32 {
+        u32::try_from(self.end_position - self.start_position).unwrap_or(u32::MAX)
+    }
+
+    /// The number of samples at the start of the packet that are discarded because of the
+    /// pre-skip.
+    pub fn discard_start(&self) -> u32 {
+        self.discard_start
+    }
+
+    /// The number of samples at the end of the packet that are discarded because of the end
+    /// trimming of the last page.
+    pub fn discard_end(&self) -> u32 {
+        self.discard_end
+    }
+}
+
+/// Reads the first Ogg Opus logical bitstream of a file.
+///
+/// The header packets are parsed and the audio packets are returned with their exact sample
+/// positions, which are computed backwards from the granule position of their page using the
+/// durations from the TOC bytes. The positions of the packets on the last page are computed
+/// forwards from the previous page, so that its granule position trims the end of the stream.
+/// Packets without a valid TOC byte have a duration of zero.
+#[derive(Clone, Debug)]
+pub struct OpusReader<R: Read + Seek> {
+    inner: FileReader<R>,
+    packet: Packet,
+    bitstream_serial_number: Option<u32>,
+    head: Option<OpusHead>,
+    tags: Option<OpusTags>,
+    /// The packets of the current page and their durations.
+    page_packets: Vec<(Vec<u8>, u32)>,
+    ready_packets: VecDeque<OpusPacket>,
+    stream_start_position: Option<u64>,
+    previous_end_position: Option<u64>,
+    is_finished: bool,
+}
+
+impl<R: Read + Seek> OpusReader<R> {
+    /// Creates a new `OpusReader`.
+    pub fn new(reader: R) -> Self {
+        Self {
+            inner: FileReader::new(reader),
+            packet: Packet::default(),
+            bitstream_serial_number: None,
+            head: None,
+            tags: None,
+            page_packets: Vec::new(),
+            ready_packets: VecDeque::new(),
+            stream_start_position: None,
+            previous_end_position: None,
+            is_finished: false,
+        }
+    }
+
+    /// Consumes the `OpusReader` and returns the reader.
+    pub fn into_inner(self) -> R {
+        self.inner.into_inner()
+    }
+
+    /// The serial number of the logical bitstream, once its identification header was read.
+    pub fn bitstream_serial_number(&self) -> Option<u32> {
+        self.bitstream_serial_number
+    }
+
+    /// The identification header, once it was read.
+    pub fn head(&self) -> Option<&OpusHead> {
+        self.head.as_ref()
+    }
+
+    /// The comment header, once it was read.
+    pub fn tags(&self) -> Option<&OpusTags> {
+        self.tags.as_ref()
+    }
+
+    /// Reads the header packets of the first Ogg Opus logical bitstream. Packets of other
+    /// logical bitstreams are skipped.
+    ///
+    /// Returns `ReadStatus::Ok` once the headers are read. Other statuses are returned like
+    /// `FileReader::next_packet()` returns them.
+    pub fn read_headers(&mut self) -> Result<ReadStatus, ReadError> {
+        while self.tags.is_none() {
+            let status = self.inner.next_packet(&mut self.packet)?;
+            if status != ReadStatus::Ok {
+                return Ok(status);
+            }
+
+            match self.bitstream_serial_number {
+                None if self.packet.is_bos() => {
+                    if let Some(head) = OpusHead::parse(self.packet.data()) {
+                        self.bitstream_serial_number = Some(self.packet.bitstream_serial_number());
+                        self.head = Some(head);
+                    }
+                }
+                Some(serial) if serial == self.packet.bitstream_serial_number() => {
+                    let tags =
+                        OpusTags::parse(self.packet.data()).ok_or(ReadError::InvalidHeader)?;
+                    self.tags = Some(tags);
+                }
+                _ => {}
+            }
+        }
+
+        Ok(ReadStatus::Ok)
+    }
+
+    /// Reads the next audio packet. The header packets are read first, if they weren't
+    /// read yet.
+    ///
+    /// Returns `ReadStatus::Eof` after the last packet of the logical bitstream. Other
+    /// statuses are returned like `FileReader::next_packet()` returns them. The positions of
+    /// the packets after lost pages are still exact, but the end trimming can only be
+    /// computed if the last page follows a page that was read.
+    pub fn next_packet(&mut self, packet: &mut OpusPacket) -> Result<ReadStatus, ReadError> {
+        let status = self.read_headers()?;
+        if status != ReadStatus::Ok {
+            return Ok(status);
+        }
+
+        loop {
+            if let Some(ready_packet) = self.ready_packets.pop_front() {
+                *packet = ready_packet;
+                return Ok(ReadStatus::Ok);
+            }
+
+            if self.is_finished {
+                return Ok(ReadStatus::Eof);
+            }
+
+            match self.inner.next_packet(&mut self.packet)? {
+                ReadStatus::Ok => {}
+                ReadStatus::Lost {
+                    bitstream_serial_number,
+                    missing_pages,
+                } => {
+                    if Some(bitstream_serial_number) == self.bitstream_serial_number {
+                        self.previous_end_position = None;
+                    }
+                    return Ok(ReadStatus::Lost {
+                        bitstream_serial_number,
+                        missing_pages,
+                    });
+                }
+                ReadStatus::Missing => {
+                    self.previous_end_position = None;
+                    return Ok(ReadStatus::Missing);
+                }
+                status => return Ok(status),
+            }
+
+            if Some(self.packet.bitstream_serial_number()) != self.bitstream_serial_number {
+                continue;
+            }
+
+            let duration = packet_duration(self.packet.data()).unwrap_or(0);
+            self.page_packets
+                .push((self.packet.data().to_vec(), duration));
+
+            if self.packet.is_last_on_page() {
+                self.finish_page(self.packet.granule_position(), self.packet.is_eos());
+            }
+            self.is_finished = self.packet.is_eos();
+        }
+    }
+
+    /// Computes the positions of the packets of the current page and queues them.
+    fn finish_page(&mut self, granule_position: u64, is_eos: bool) {
+        let total_duration: u64 = self
+            .page_packets
+            .iter()
+            .map(|(_, duration)| u64::from(*duration))
+            .sum();
+
+        // The first page can only be trimmed at the end if it is the last page as well.
+        let start_position = match (is_eos, self.previous_end_position) {
+            (true, Some(previous_end_position)) => previous_end_position,
+            (true, None) if self.stream_start_position.is_none() => 0,
+            _ => granule_position.saturating_sub(total_duration),
+        };
+
+        let stream_start_position = *self.stream_start_position.get_or_insert(start_position);
+        let pre_skip = self.head.as_ref().map_or(0, |head| head.pre_skip());
+        let pre_skip_end_position = stream_start_position + u64::from(pre_skip);
+
+        let mut position = start_position;
+        for (data, duration) in self.page_packets.drain(..) {
+            let end_position = position + u64::from(duration);
+            let discard_end = if is_eos {
+                clamp_samples(end_position.saturating_sub(granule_position), duration)
+            } else {
+                0
+            };
+
+            self.ready_packets.push_back(OpusPacket {
+                data,
+                start_position: position,
+                end_position,
+                discard_start: clamp_samples(
+                    pre_skip_end_position.saturating_sub(position),
+                    duration,
+                ),
+                discard_end,
+            });
+
+            position = end_position;
+        }
+
+        self.previous_end_position = Some(position);
+    }
+}
+
+/// Limits the given number of samples to the duration of a packet.
+fn clamp_samples(samples: u64, duration: u32) -> u32 {
+    u32::try_from(samples).map_or(duration, |samples| samples.min(duration))
+}
+
+#[cfg(all(test, feature = "writer"))]
+mod tests {
+    #![allow(clippy::panic)]
+    #![allow(clippy::unwrap_used)]
+
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::crc32::crc32;
+    use crate::{OpusWriter, StreamWriter, CRC32_RANGE, EOS_VALUE, HEADER_TYPE_INDEX};
+
+    /// A CELT packet of 20 ms.
+    const AUDIO_PACKET: [u8; 2] = [0xF8, 0xAA];
+
+    fn read_packets(data: Vec<u8>) -> (OpusReader<Cursor<Vec<u8>>>, Vec<OpusPacket>) {
+        let mut reader = OpusReader::new(Cursor::new(data));
+        let mut packets = Vec::new();
+        loop {
+            let mut packet = OpusPacket::default();
+            match reader.next_packet(&mut packet).unwrap() {
+                ReadStatus::Ok => packets.push(packet),
+                ReadStatus::Eof => return (reader, packets),
+                status => panic!("unexpected read status: {:?}", status),
+            }
+        }
+    }
+
+    fn positions(packets: &[OpusPacket]) -> Vec<(u64, u64, u32, u32)> {
+        packets
+            .iter()
+            .map(|p| {
+                (
+                    p.start_position(),
+                    p.end_position(),
+                    p.discard_start(),
+                    p.discard_end(),
+                )
+            })
+            .collect()
+    }
+
+    /// Writes the header pages and pages with the given packet counts and granule positions.
+    /// The last page is marked as the end of the stream.
+    fn write_stream(pages: &[(usize, u64)]) -> Vec<u8> {
+        let head = OpusHead::new(1, 312, 48_000).unwrap();
+        let tags = OpusTags::new("test").to_packet().unwrap();
+
+        let mut writer = StreamWriter::new(Cursor::new(Vec::new()));
+        writer.begin_logical_stream(3, &[0x1]).unwrap();
+        writer.begin_logical_stream(7, &head.to_packet()).unwrap();
+        writer.push_packet(7, &tags, 0).unwrap();
+        writer.flush(7).unwrap();
+        writer.end_logical_stream(3, &[0x2], 0).unwrap();
+
+        for (count, granule_position) in pages.iter() {
+            for _ in 0..*count {
+                writer
+                    .push_packet(7, &AUDIO_PACKET, *granule_position)
+                    .unwrap();
+            }
+            writer.flush(7).unwrap();
+        }
+        let mut data = writer.into_inner().into_inner();
+
+        let count = pages.last().unwrap().0;
+        let offset = data.len() - (27 + count * (1 + AUDIO_PACKET.len()));
+        data[offset + HEADER_TYPE_INDEX] |= EOS_VALUE;
+        data[offset + CRC32_RANGE.start..offset + CRC32_RANGE.end].copy_from_slice(&[0; 4]);
+        let checksum = crc32(&data[offset..]);
+        data[offset + CRC32_RANGE.start..offset + CRC32_RANGE.end]
+            .copy_from_slice(&checksum.to_le_bytes());
+
+        data
+    }
+
+    #[test]
+    fn test_opus_reader() {
+        let head = OpusHead::new(2, 312, 44_100).unwrap();
+        let mut tags = OpusTags::new("test");
+        tags.add_comment("TITLE", "Foo");
+
+        let mut writer = OpusWriter::new(Vec::new(), 7, &head, &tags).unwrap();
+        for _ in 0..3 {
+            writer.push_packet(&AUDIO_PACKET).unwrap();
+        }
+        writer.flush().unwrap();
+        for _ in 0..2 {
+            writer.push_packet(&AUDIO_PACKET).unwrap();
+        }
+        let data = writer.finish(&AUDIO_PACKET, 500).unwrap();
+
+        let (reader, packets) = read_packets(data);
+        assert_eq!(reader.bitstream_serial_number(), Some(7));
+        assert_eq!(reader.head(), Some(&head));
+        assert_eq!(reader.tags().unwrap().comment("title"), Some("Foo"));
+
+        assert_eq!(
+            positions(&packets),
+            [
+                (0, 960, 312, 0),
+                (960, 1920, 0, 0),
+                (1920, 2880, 0, 0),
+                (2880, 3840, 0, 0),
+                (3840, 4800, 0, 0),
+                (4800, 5760, 0, 500),
+            ]
+        );
+        assert_eq!(packets[0].data(), &AUDIO_PACKET);
+        assert_eq!(packets[0].duration(), 960);
+    }
+
+    #[test]
+    fn test_start_offset() {
+        // The stream starts at a granule position other than zero.
+        let data = write_stream(&[(2, 10_000), (2, 11_000)]);
+        let (_, packets) = read_packets(data);
+        assert_eq!(
+            positions(&packets),
+            [
+                (8080, 9040, 312, 0),
+                (9040, 10_000, 0, 0),
+                (10_000, 10_960, 0, 0),
+                (10_960, 11_920, 0, 920),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_single_page() {
+        // The first page is the last page, so it starts at zero and trims the end.
+        let data = write_stream(&[(2, 1000)]);
+        let (_, packets) = read_packets(data);
+        assert_eq!(positions(&packets), [(0, 960, 312, 0), (960, 1920, 0, 920)]);
+    }
+
+    #[test]
+    fn test_invalid_tags() {
+        let head = OpusHead::new(1, 312, 48_000).unwrap();
+
+        let mut writer = StreamWriter::new(Vec::new());
+        writer.begin_logical_stream(7, &head.to_packet()).unwrap();
+        writer.end_logical_stream(7, &AUDIO_PACKET, 960).unwrap();
+
+        let mut reader = OpusReader::new(Cursor::new(writer.into_inner()));
+        assert!(matches!(
+            reader.read_headers(),
+            Err(ReadError::InvalidHeader)
+        ));
+    }
+}
@@ -0,0 +1,594 @@
+//! Seek index for seeking without bisecting the file.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+
+use super::chain::find_chain;
+use super::{BitStreamReader, FileReader, SeekResult};
+use crate::{GranuleMapper, ReadError};
+
+/// Magic bytes at the start of a serialized seek index.
+const MAGIC: &[u8; 4] = b"OgSI";
+/// Version of the serialization format.
+const VERSION: u8 = 1;
+
+/// Index of the pages of all logical bitstreams of a file.
+///
+/// The index is built by a single scan over the page headers of a file and records the byte
+/// offset and granule position of every page that finishes a packet. It can be serialized into
+/// a compact sidecar format, so that it only needs to be built once.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SeekIndex {
+    chains: Vec<IndexedChain>,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+struct IndexedChain {
+    byte_range: Range<u64>,
+    streams: Vec<IndexedStream>,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+struct IndexedStream {
+    bitstream_serial_number: u32,
+    entries: Vec<IndexEntry>,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+struct IndexEntry {
+    /// Start of the first page of the packets finished on the page.
+    byte_offset: u64,
+    granule_position: u64,
+    page_sequence_number: u32,
+}
+
+impl SeekIndex {
+    /// Returns true if the index contains no pages.
+    pub fn is_empty(&self) -> bool {
+        self.chains
+            .iter()
+            .flat_map(|chain| chain.streams.iter())
+            .all(|stream| stream.entries.is_empty())
+    }
+
+    /// Serializes the index into the writer.
+    ///
+    /// Offsets, granule positions and sequence numbers are delta encoded as variable length
+    /// integers.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        let mut buffer = Vec::new();
+        buffer.extend_from_slice(MAGIC);
+        buffer.push(VERSION);
+
+        write_varint(&mut buffer, len_u64(self.chains.len()));
+        for chain in self.chains.iter() {
+            write_varint(&mut buffer, chain.byte_range.start);
+            write_varint(&mut buffer, chain.byte_range.end - chain.byte_range.start);
+            write_varint(&mut buffer, len_u64(chain.streams.len()));
+
+            for stream in chain.streams.iter() {
+                buffer.extend_from_slice(&stream.bitstream_serial_number.to_le_bytes());
+                write_varint(&mut buffer, len_u64(stream.entries.len()));
+
+                let mut previous = IndexEntry {
+                    byte_offset: chain.byte_range.start,
+                    ..Default::default()
+                };
+                for entry in stream.entries.iter() {
+                    write_varint(&mut buffer, entry.byte_offset - previous.byte_offset);
+                    write_varint(
+                        &mut buffer,
+                        entry
+                            .granule_position
+                            .wrapping_sub(previous.granule_position),
+                    );
+                    write_varint(
+                        &mut buffer,
+                        u64::from(
+                            entry
+                                .page_sequence_number
+                                .wrapping_sub(previous.page_sequence_number),
+                        ),
+                    );
+                    previous = *entry;
+                }
+            }
+        }
+
+        writer.write_all(&buffer)
+    }
+
+    /// Deserializes an index that was written by `write_to()`.
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<SeekIndex, ReadError> {
+        let mut header = [0_u8; 5];
+        reader.read_exact(&mut header)?;
+        if &header[..4] != MAGIC || header[4] != VERSION {
+            return Err(ReadError::InvalidSeekIndex);
+        }
+
+        let mut index = SeekIndex::default();
+
+        let chain_count = read_varint(reader)?;
+        for _ in 0..chain_count {
+            let start = read_varint(reader)?;
+            let end = start
+                .checked_add(read_varint(reader)?)
+                .ok_or(ReadError::InvalidSeekIndex)?;
+            let mut chain = IndexedChain {
+                byte_range: start..end,
+                streams: Vec::new(),
+            };
+
+            let stream_count = read_varint(reader)?;
+            for _ in 0..stream_count {
+                let mut serial = [0_u8; 4];
+                reader.read_exact(&mut serial)?;
+                let mut stream = IndexedStream {
+                    bitstream_serial_number: u32::from_le_bytes(serial),
+                    entries: Vec::new(),
+                };
+
+                let mut previous = IndexEntry {
+                    byte_offset: start,
+                    ..Default::default()
+                };
+                let entry_count = read_varint(reader)?;
+                for _ in 0..entry_count {
+                    let byte_offset = previous
+                        .byte_offset
+                        .checked_add(read_varint(reader)?)
+                        .filter(|offset| *offset < end)
+                        .ok_or(ReadError::InvalidSeekIndex)?;
+                    let granule_position =
+                        previous.granule_position.wrapping_add(read_varint(reader)?);
+                    let sequence_delta = u32::try_from(read_varint(reader)?)
+                        .map_err(|_| ReadError::InvalidSeekIndex)?;
+
+                    previous = IndexEntry {
+                        byte_offset,
+                        granule_position,
+                        page_sequence_number: previous
+                            .page_sequence_number
+                            .wrapping_add(sequence_delta),
+                    };
+                    stream.entries.push(previous);
+                }
+
+                chain.streams.push(stream);
+            }
+
+            index.chains.push(chain);
+        }
+
+        Ok(index)
+    }
+
+    /// Returns the first chain of the logical bitstream whose granule positions reach the
+    /// target, or its last chain if there is none. Granule positions are compared by the
+    /// given order.
+    fn find(
+        &self,
+        bitstream_serial_number: u32,
+        target_granule_position: u64,
+        order: &dyn Fn(u64) -> u64,
+    ) -> Option<(&IndexedChain, &IndexedStream)> {
+        let candidates = self.chains.iter().filter_map(|chain| {
+            let stream = chain
+                .streams
+                .iter()
+                .find(|s| s.bitstream_serial_number == bitstream_serial_number)?;
+            let last_granule_position = stream.entries.last().map(|entry| entry.granule_position);
+
+            Some(((chain, stream), last_granule_position.map(order)))
+        });
+
+        find_chain(candidates, &order(target_granule_position))
+    }
+}
+
+impl<R: Read + Seek> FileReader<R> {
+    /// Builds a seek index with a single scan over the page headers of the file.
+    ///
+    /// The position of the reader is not changed.
+    pub fn seek_index(&mut self) -> Result<SeekIndex, ReadError> {
+        let position = self.reader.stream_position()?;
+
+        let mut scanner = BitStreamReader::default();
+        let index = scanner.build_seek_index(&mut self.reader);
+
+        self.reader.seek(SeekFrom::Start(position))?;
+
+        index
+    }
+
+    /// Seeks to the first page that has a granule position greater or equal to the given one
+    /// for the given logical bitstream, by looking it up in the given index.
+    ///
+    /// Targets after the end of the logical bitstream land on its last page and the result
+    /// is marked as clamped. The index needs to be built from the same file.
+    pub fn seek_with_index(
+        &mut self,
+        index: &SeekIndex,
+        bitstream_serial_number: u32,
+        target_granule_position: u64,
+    ) -> Result<SeekResult, ReadError> {
+        self.seek_with_index_by(
+            index,
+            bitstream_serial_number,
+            target_granule_position,
+            &|granule_position| granule_position,
+            &|_| None,
+        )
+    }
+
+    /// Seeks like `seek_keyframe()`, but looks the pages of the target and its keyframe up in
+    /// the given index.
+    ///
+    /// The index needs to be built from the same file.
+    pub fn seek_keyframe_with_index(
+        &mut self,
+        index: &SeekIndex,
+        bitstream_serial_number: u32,
+        target_granule_position: u64,
+        mapper: &dyn GranuleMapper,
+    ) -> Result<SeekResult, ReadError> {
+        self.seek_with_index_by(
+            index,
+            bitstream_serial_number,
+            target_granule_position,
+            &|granule_position| mapper.granule_order(granule_position),
+            &|granule_position| mapper.keyframe_granule(granule_position),
+        )
+    }
+
+    /// Seeks to the page of the target in the index. Granule positions are compared by the
+    /// given order and the page of the keyframe of the target is used, if there is one.
+    fn seek_with_index_by(
+        &mut self,
+        index: &SeekIndex,
+        bitstream_serial_number: u32,
+        target_granule_position: u64,
+        order: &dyn Fn(u64) -> u64,
+        keyframe_granule: &dyn Fn(u64) -> Option<u64>,
+    ) -> Result<SeekResult, ReadError> {
+        let (chain, stream) = index
+            .find(bitstream_serial_number, target_granule_position, order)
+            .ok_or(ReadError::UnknownBitstreamSerialNumber)?;
+
+        let entries = &stream.entries;
+        let target_order = order(target_granule_position);
+        let position =
+            entries.partition_point(|entry| order(entry.granule_position) < target_order);
+
+        let mut result = SeekResult {
+            byte_offset: chain.byte_range.start,
+            is_clamped: position == entries.len(),
+            ..Default::default()
+        };
+
+        let landed = usize::min(position, entries.len().saturating_sub(1));
+        let mut entry = entries.get(landed);
+
+        if let Some(mut keyframe) = entry.and_then(|entry| keyframe_granule(entry.granule_position))
+        {
+            // The keyframe of the page we landed on is the keyframe of the target, if it's not
+            // after the target. Otherwise the keyframe of the page before is used, which is
+            // the same or an earlier one.
+            if order(keyframe) > target_order {
+                keyframe = landed
+                    .checked_sub(1)
+                    .and_then(|before| entries.get(before))
+                    .and_then(|entry| keyframe_granule(entry.granule_position))
+                    .unwrap_or(0);
+            }
+
+            let keyframe_order = order(keyframe);
+            let position =
+                entries.partition_point(|entry| order(entry.granule_position) < keyframe_order);
+            entry = entries.get(position).or(entry);
+        }
+
+        if let Some(entry) = entry {
+            result.byte_offset = entry.byte_offset;
+            result.granule_position = Some(entry.granule_position);
+            result.page_sequence_number = Some(entry.page_sequence_number);
+        }
+
+        self.inner.discard_packets();
+        self.inner.page_head = 0;
+        self.inner.skipped_bytes = 0;
+        self.reader.seek(SeekFrom::Start(result.byte_offset))?;
+
+        Ok(result)
+    }
+}
+
+impl BitStreamReader {
+    /// Walks all pages of the file and indexes the pages that finish packets. Pages with a
+    /// wrong checksum are skipped.
+    fn build_seek_index<R: Read + Seek>(&mut self, reader: &mut R) -> Result<SeekIndex, ReadError> {
+        let mut index = SeekIndex::default();
+        let mut packet_starts: HashMap<u32, u64> = HashMap::new();
+        let mut in_headers = false;
+        let mut offset = 0;
+
+        while let Some(page) = self.probe_next_page(reader, offset)? {
+            offset = page.end;
+
+            // A BOS page after the BOS pages of the current chain starts a new chain.
+            if page.is_bos() {
+                let is_new_chain = match index.chains.last() {
+                    Some(chain) => {
+                        !in_headers
+                            || chain
+                                .streams
+                                .iter()
+                                .any(|s| s.bitstream_serial_number == page.bitstream_serial_number)
+                    }
+                    None => true,
+                };
+
+                if is_new_chain {
+                    index.chains.push(IndexedChain {
+                        byte_range: page.start..page.end,
+                        streams: Vec::new(),
+                    });
+                    packet_starts.clear();
+                }
+
+                if let Some(chain) = index.chains.last_mut() {
+                    chain.streams.push(IndexedStream {
+                        bitstream_serial_number: page.bitstream_serial_number,
+                        entries: Vec::new(),
+                    });
+                }
+            }
+            in_headers = page.is_bos();
+
+            let chain = match index.chains.last_mut() {
+                Some(chain) => chain,
+                None => continue,
+            };
+
+            let stream = match chain
+                .streams
+                .iter_mut()
+                .find(|s| s.bitstream_serial_number == page.bitstream_serial_number)
+            {
+                Some(stream) => stream,
+                None => continue,
+            };
+
+            chain.byte_range.end = page.end;
+
+            let packet_start = *packet_starts
+                .entry(page.bitstream_serial_number)
+                .or_insert(page.start);
+
+            if page.granule_position != u64::MAX {
+                stream.entries.push(IndexEntry {
+                    byte_offset: packet_start,
+                    granule_position: page.granule_position,
+                    page_sequence_number: page.page_sequence_number,
+                });
+                packet_starts.remove(&page.bitstream_serial_number);
+            }
+        }
+
+        Ok(index)
+    }
+}
+
+/// Returns the length as an `u64`.
+fn len_u64(len: usize) -> u64 {
+    u64::try_from(len).unwrap_or(u64::MAX)
+}
+
+/// Writes the value as an LEB128 variable length integer.
+fn write_varint(buffer: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7F).to_le_bytes()[0];
+        value >>= 7;
+        if value == 0 {
+            buffer.push(byte);
+            return;
+        }
+        buffer.push(byte | 0x80);
+    }
+}
+
+/// Reads an LEB128 variable length integer.
+fn read_varint<R: Read>(reader: &mut R) -> Result<u64, ReadError> {
+    let mut value = 0_u64;
+    let mut byte = [0_u8; 1];
+
+    for shift in (0..64).step_by(7) {
+        reader.read_exact(&mut byte)?;
+        let bits = u64::from(byte[0] & 0x7F);
+        if shift == 63 && bits > 1 {
+            return Err(ReadError::InvalidSeekIndex);
+        }
+
+        value |= bits << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+
+    Err(ReadError::InvalidSeekIndex)
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::panic)]
+    #![allow(clippy::unwrap_used)]
+
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn test_varint() {
+        let values = [0, 1, 127, 128, 300, u64::from(u32::MAX), u64::MAX];
+
+        let mut buffer = Vec::new();
+        for value in values.iter() {
+            write_varint(&mut buffer, *value);
+        }
+
+        let mut cursor = Cursor::new(buffer);
+        for value in values.iter() {
+            assert_eq!(read_varint(&mut cursor).unwrap(), *value);
+        }
+    }
+
+    #[test]
+    fn test_read_invalid_index() {
+        let res = SeekIndex::read_from(&mut Cursor::new(b"OggS\x01".to_vec()));
+        assert!(matches!(res, Err(ReadError::InvalidSeekIndex)));
+
+        let res = SeekIndex::read_from(&mut Cursor::new(b"OgSI\x01\x01".to_vec()));
+        assert!(matches!(res, Err(ReadError::IoError(_))));
+    }
+
+    #[test]
+    #[cfg(feature = "writer")]
+    fn test_seek_with_index() {
+        use crate::{Packet, ReadStatus, StreamWriter};
+
+        let mut sw = StreamWriter::new(Cursor::new(vec![]));
+        for chain in 0..2_u64 {
+            // The serial number is reused by the second chain.
+            sw.begin_logical_stream(1, &[0x1]).unwrap();
+            sw.begin_logical_stream(2, &[0x2]).unwrap();
+            for i in 1..=50_u64 {
+                let granule_position = chain * 100_000 + i * 1000;
+                let mut data = granule_position.to_le_bytes().to_vec();
+                data.resize(2000, 0xAA);
+                sw.push_packet(1, &data, granule_position).unwrap();
+                sw.push_packet(2, &data, granule_position).unwrap();
+                sw.flush(1).unwrap();
+                sw.flush(2).unwrap();
+            }
+            sw.end_logical_stream(1, &[0xBB], chain * 100_000 + 50_500)
+                .unwrap();
+            sw.end_logical_stream(2, &[0xBB], chain * 100_000 + 50_500)
+                .unwrap();
+        }
+
+        let mut fr = crate::FileReader::new(sw.into_inner());
+        let index = fr.seek_index().unwrap();
+        assert!(!index.is_empty());
+        assert_eq!(index.chains.len(), 2);
+        assert_eq!(
+            index.chains[1].byte_range.end,
+            fr.streams().unwrap()[1].byte_range().end
+        );
+
+        let mut buffer = Vec::new();
+        index.write_to(&mut buffer).unwrap();
+        let index = SeekIndex::read_from(&mut Cursor::new(buffer)).unwrap();
+
+        let mut packet = Packet::default();
+        for target in [20_000, 20_500, 120_000].iter() {
+            let result = fr.seek_with_index(&index, 2, *target).unwrap();
+            assert!(!result.is_clamped());
+            assert_eq!(
+                result.granule_position(),
+                fr.seek(2, *target).unwrap().granule_position()
+            );
+            fr.seek_with_index(&index, 2, *target).unwrap();
+
+            loop {
+                assert_eq!(fr.next_packet(&mut packet).unwrap(), ReadStatus::Ok);
+                if packet.bitstream_serial_number() == 2 {
+                    break;
+                }
+            }
+            let expected = result.granule_position().unwrap().to_le_bytes();
+            assert_eq!(&packet.data()[..8], &expected);
+        }
+
+        let result = fr.seek_with_index(&index, 1, 200_000).unwrap();
+        assert!(result.is_clamped());
+        assert_eq!(result.granule_position(), Some(150_500));
+
+        assert!(matches!(
+            fr.seek_with_index(&index, 3, 1000),
+            Err(ReadError::UnknownBitstreamSerialNumber)
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "writer")]
+    fn test_seek_index_corrupt_page() {
+        use crate::StreamWriter;
+
+        let mut sw = StreamWriter::new(Cursor::new(vec![]));
+        sw.begin_logical_stream(1, &[0x1]).unwrap();
+        for i in 1..=10_u8 {
+            sw.push_packet(1, &[i; 1000], u64::from(i) * 1000).unwrap();
+            sw.flush(1).unwrap();
+        }
+        sw.end_logical_stream(1, &[0xBB], 10_500).unwrap();
+
+        // Corrupts the payload of the fifth page.
+        let mut d = sw.into_inner().into_inner();
+        let position = d.windows(1000).position(|w| w == [5; 1000]).unwrap();
+        d[position] = 0;
+
+        let mut fr = crate::FileReader::new(Cursor::new(d));
+        let index = fr.seek_index().unwrap();
+        let granule_positions: Vec<u64> = index.chains[0].streams[0]
+            .entries
+            .iter()
+            .map(|entry| entry.granule_position)
+            .collect();
+        assert_eq!(
+            granule_positions,
+            vec![0, 1000, 2000, 3000, 4000, 6000, 7000, 8000, 9000, 10_000, 10_500]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "writer")]
+    fn test_seek_keyframe_with_index() {
+        use crate::StreamWriter;
+
+        // Keyframes every 10 frames. Pages contain the frames 0, 1-2, 3-4, ...
+        let granule_position = |frame: u64| ((frame / 10 * 10) << 6) | (frame % 10);
+        let mut sw = StreamWriter::new(Cursor::new(vec![]));
+        sw.begin_logical_stream(1, b"\x80theora").unwrap();
+        for frame in 0..=60_u64 {
+            sw.push_packet(1, &[0xAA; 1000], granule_position(frame))
+                .unwrap();
+            if frame % 2 == 0 {
+                sw.flush(1).unwrap();
+            }
+        }
+        sw.end_logical_stream(1, &[0xBB], granule_position(61))
+            .unwrap();
+
+        let mapper = crate::TheoraMapper::new(6, 25, 1).unwrap();
+        let mut fr = crate::FileReader::new(sw.into_inner());
+        let index = fr.seek_index().unwrap();
+
+        // The frame 25 is not a keyframe, so its granule position is only comparable by the
+        // order of the mapper.
+        for target in [25 << 6, 19 << 6, granule_position(33), 0].iter() {
+            let expected = fr.seek_keyframe(1, *target, &mapper).unwrap();
+            let result = fr
+                .seek_keyframe_with_index(&index, 1, *target, &mapper)
+                .unwrap();
+            assert_eq!(result.byte_offset(), expected.byte_offset());
+            assert_eq!(result.granule_position(), expected.granule_position());
+        }
+
+        let result = fr
+            .seek_keyframe_with_index(&index, 1, 25 << 6, &mapper)
+            .unwrap();
+        assert_eq!(result.granule_position(), Some(granule_position(20)));
+    }
+}
@@ -0,0 +1,722 @@
+//! Parsing of the Ogg Skeleton 4.0 packets.
+
+use std::convert::{TryFrom, TryInto};
+use std::time::Duration;
+
+use crate::granule::{fraction_to_time, NANOS_PER_SEC};
+
+/// The magic bytes of the fishead packet.
+const FISHEAD_MAGIC: &[u8] = b"fishead\0";
+/// The magic bytes of the fisbone packet.
+const FISBONE_MAGIC: &[u8] = b"fisbone\0";
+/// The magic bytes of the index packet.
+const INDEX_MAGIC: &[u8] = b"index\0";
+
+/// The size of a fishead packet of version 3.
+const FISHEAD_V3_SIZE: usize = 64;
+/// The size of a fishead packet of version 4.
+const FISHEAD_V4_SIZE: usize = 80;
+/// The offset of the message header fields of a fisbone packet.
+const FISBONE_HEADERS_OFFSET: usize = 52;
+/// The offset of the message header fields, relative to the field that stores it.
+const FISBONE_HEADERS_FIELD: u32 = 44;
+/// The offset of the keypoints of an index packet.
+const INDEX_KEYPOINTS_OFFSET: usize = 42;
+/// The biggest size of a variable length integer of an index packet.
+const MAX_VARINT_SIZE: usize = 10;
+
+/// The fishead packet, which is the BOS packet of the Skeleton logical bitstream.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Fishead {
+    version_major: u16,
+    version_minor: u16,
+    presentation_time: (i64, i64),
+    base_time: (i64, i64),
+    utc: [u8; 20],
+    segment_length: u64,
+    content_byte_offset: u64,
+}
+
+impl Fishead {
+    /// Creates a new fishead of version 4.0 with the given presentation and base time as
+    /// numerator and denominator.
+    pub fn new(presentation_time: (i64, i64), base_time: (i64, i64)) -> Self {
+        Self {
+            version_major: 4,
+            version_minor: 0,
+            presentation_time,
+            base_time,
+            utc: [0; 20],
+            segment_length: 0,
+            content_byte_offset: 0,
+        }
+    }
+
+    /// Parses a fishead packet of version 3 or 4.
+    ///
+    /// Returns `None` if the packet is not a fishead packet or is too short.
+    pub fn parse(packet: &[u8]) -> Option<Self> {
+        if !packet.starts_with(FISHEAD_MAGIC) {
+            return None;
+        }
+
+        let version_major = u16::from_le_bytes(read_array(packet, 8)?);
+        let (segment_length, content_byte_offset) = if version_major >= 4 {
+            (
+                u64::from_le_bytes(read_array(packet, 64)?),
+                u64::from_le_bytes(read_array(packet, 72)?),
+            )
+        } else if packet.len() >= FISHEAD_V3_SIZE {
+            (0, 0)
+        } else {
+            return None;
+        };
+
+        Some(Self {
+            version_major,
+            version_minor: u16::from_le_bytes(read_array(packet, 10)?),
+            presentation_time: read_rational(packet, 12)?,
+            base_time: read_rational(packet, 28)?,
+            utc: read_array(packet, 44)?,
+            segment_length,
+            content_byte_offset,
+        })
+    }
+
+    /// The major and minor version of the Skeleton logical bitstream.
+    pub fn version(&self) -> (u16, u16) {
+        (self.version_major, self.version_minor)
+    }
+
+    /// The numerator and denominator of the presentation time of the first sample.
+    pub fn presentation_time(&self) -> (i64, i64) {
+        self.presentation_time
+    }
+
+    /// The numerator and denominator of the time that maps to granule position `0`.
+    pub fn base_time(&self) -> (i64, i64) {
+        self.base_time
+    }
+
+    /// The UTC time of the first sample, as a string of 20 characters.
+    pub fn utc(&self) -> &[u8; 20] {
+        &self.utc
+    }
+
+    /// The length of the physical bitstream in bytes. Is `0` before version 4.
+    pub fn segment_length(&self) -> u64 {
+        self.segment_length
+    }
+
+    /// The offset of the first non-header page in bytes. Is `0` before version 4.
+    pub fn content_byte_offset(&self) -> u64 {
+        self.content_byte_offset
+    }
+
+    /// Sets the length of the physical bitstream in bytes.
+    pub fn set_segment_length(&mut self, segment_length: u64) {
+        self.segment_length = segment_length;
+    }
+
+    /// Sets the offset of the first non-header page in bytes.
+    pub fn set_content_byte_offset(&mut self, content_byte_offset: u64) {
+        self.content_byte_offset = content_byte_offset;
+    }
+
+    /// Returns the fishead packet.
+    pub fn to_packet(&self) -> Vec<u8> {
+        let mut packet = Vec::with_capacity(FISHEAD_V4_SIZE);
+        packet.extend_from_slice(FISHEAD_MAGIC);
+        packet.extend_from_slice(&self.version_major.to_le_bytes());
+        packet.extend_from_slice(&self.version_minor.to_le_bytes());
+        write_rational(&mut packet, self.presentation_time);
+        write_rational(&mut packet, self.base_time);
+        packet.extend_from_slice(&self.utc);
+        if self.version_major >= 4 {
+            packet.extend_from_slice(&self.segment_length.to_le_bytes());
+            packet.extend_from_slice(&self.content_byte_offset.to_le_bytes());
+        }
+        packet
+    }
+}
+
+/// The fisbone packet, which describes one logical bitstream of the chain.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Fisbone {
+    bitstream_serial_number: u32,
+    header_packets: u32,
+    granule_rate: (i64, i64),
+    base_granule: i64,
+    preroll: u32,
+    granule_shift: u8,
+    message_headers: Vec<(String, String)>,
+}
+
+impl Fisbone {
+    /// Creates a new fisbone for the logical bitstream with the given content type, granule
+    /// rate as numerator and denominator and preroll.
+    pub fn new(
+        bitstream_serial_number: u32,
+        content_type: &str,
+        granule_rate: (i64, i64),
+        preroll: u32,
+    ) -> Self {
+        Self {
+            bitstream_serial_number,
+            header_packets: 0,
+            granule_rate,
+            base_granule: 0,
+            preroll,
+            granule_shift: 0,
+            message_headers: vec![("Content-Type".to_owned(), content_type.to_owned())],
+        }
+    }
+
+    /// Parses a fisbone packet.
+    ///
+    /// Returns `None` if the packet is not a fisbone packet or is malformed.
+    pub fn parse(packet: &[u8]) -> Option<Self> {
+        if !packet.starts_with(FISBONE_MAGIC) {
+            return None;
+        }
+
+        // The offset of the message header fields is relative to its own field.
+        let headers_offset = u32::from_le_bytes(read_array(packet, 8)?);
+        let headers_start = usize::try_from(headers_offset).ok()?.checked_add(8)?;
+        if headers_start < FISBONE_HEADERS_OFFSET {
+            return None;
+        }
+
+        let message_headers = std::str::from_utf8(packet.get(headers_start..)?)
+            .ok()?
+            .split("\r\n")
+            .map(|line| line.trim_end_matches('\0'))
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                let (name, value) = line.split_once(':')?;
+                Some((name.trim().to_owned(), value.trim().to_owned()))
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        Some(Self {
+            bitstream_serial_number: u32::from_le_bytes(read_array(packet, 12)?),
+            header_packets: u32::from_le_bytes(read_array(packet, 16)?),
+            granule_rate: read_rational(packet, 20)?,
+            base_granule: i64::from_le_bytes(read_array(packet, 36)?),
+            preroll: u32::from_le_bytes(read_array(packet, 44)?),
+            granule_shift: *packet.get(48)?,
+            message_headers,
+        })
+    }
+
+    /// Unique serial ID of the described logical bitstream.
+    pub fn bitstream_serial_number(&self) -> u32 {
+        self.bitstream_serial_number
+    }
+
+    /// The number of header packets of the described logical bitstream.
+    pub fn header_packets(&self) -> u32 {
+        self.header_packets
+    }
+
+    /// The numerator and denominator of the granule positions per second.
+    pub fn granule_rate(&self) -> (i64, i64) {
+        self.granule_rate
+    }
+
+    /// The granule position of the first data packet.
+    pub fn base_granule(&self) -> i64 {
+        self.base_granule
+    }
+
+    /// The number of packets that need to be decoded before a seek target.
+    pub fn preroll(&self) -> u32 {
+        self.preroll
+    }
+
+    /// The granule shift of codecs with split granule positions.
+    pub fn granule_shift(&self) -> u8 {
+        self.granule_shift
+    }
+
+    /// The message header fields as name and value pairs, like `Content-Type`.
+    pub fn message_headers(&self) -> &[(String, String)] {
+        self.message_headers.as_ref()
+    }
+
+    /// Returns the value of the first message header field with the given name, which is
+    /// compared case insensitive.
+    pub fn message_header(&self, name: &str) -> Option<&str> {
+        self.message_headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The content type of the described logical bitstream, like `audio/vorbis`.
+    pub fn content_type(&self) -> Option<&str> {
+        self.message_header("Content-Type")
+    }
+
+    /// Sets the number of header packets of the described logical bitstream.
+    pub fn set_header_packets(&mut self, header_packets: u32) {
+        self.header_packets = header_packets;
+    }
+
+    /// Sets the granule position of the first data packet.
+    pub fn set_base_granule(&mut self, base_granule: i64) {
+        self.base_granule = base_granule;
+    }
+
+    /// Sets the granule shift of codecs with split granule positions.
+    pub fn set_granule_shift(&mut self, granule_shift: u8) {
+        self.granule_shift = granule_shift;
+    }
+
+    /// Adds a message header field, like `Role` or `Name`.
+    pub fn add_message_header(&mut self, name: &str, value: &str) {
+        self.message_headers
+            .push((name.to_owned(), value.to_owned()));
+    }
+
+    /// Returns the time of the given granule position as numerator and denominator,
+    /// measured from the base granule.
+    ///
+    /// Returns `None` if the granule position is in front of the base granule or the
+    /// granule rate is invalid.
+    pub fn granule_time(&self, granule_position: u64) -> Option<(i64, i64)> {
+        let (numerator, denominator) = self.granule_rate;
+        if numerator <= 0 || denominator <= 0 || self.granule_shift > 63 {
+            return None;
+        }
+
+        // Split granule positions count the frames since the last keyframe in the low bits.
+        let mask = (1_u64 << self.granule_shift) - 1;
+        let units =
+            (granule_position >> self.granule_shift).checked_add(granule_position & mask)?;
+        let units = i64::try_from(units)
+            .ok()?
+            .checked_sub(self.base_granule)
+            .filter(|units| *units >= 0)?;
+
+        Some((units.checked_mul(denominator)?, numerator))
+    }
+
+    /// Returns the fisbone packet.
+    pub fn to_packet(&self) -> Vec<u8> {
+        let mut packet = Vec::with_capacity(FISBONE_HEADERS_OFFSET);
+        packet.extend_from_slice(FISBONE_MAGIC);
+        packet.extend_from_slice(&FISBONE_HEADERS_FIELD.to_le_bytes());
+        packet.extend_from_slice(&self.bitstream_serial_number.to_le_bytes());
+        packet.extend_from_slice(&self.header_packets.to_le_bytes());
+        write_rational(&mut packet, self.granule_rate);
+        packet.extend_from_slice(&self.base_granule.to_le_bytes());
+        packet.extend_from_slice(&self.preroll.to_le_bytes());
+        packet.extend_from_slice(&[self.granule_shift, 0, 0, 0]);
+        for (name, value) in self.message_headers.iter() {
+            packet.extend_from_slice(name.as_bytes());
+            packet.extend_from_slice(b": ");
+            packet.extend_from_slice(value.as_bytes());
+            packet.extend_from_slice(b"\r\n");
+        }
+        packet
+    }
+}
+
+/// The index packet, which maps the times of keyframes of one logical bitstream to the
+/// offsets of the pages they start on.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SkeletonIndex {
+    bitstream_serial_number: u32,
+    timestamp_denominator: i64,
+    first_sample_time: i64,
+    last_sample_time: i64,
+    keypoints: Vec<Keypoint>,
+}
+
+/// A keypoint of a Skeleton index.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Keypoint {
+    byte_offset: u64,
+    timestamp: i64,
+}
+
+impl Keypoint {
+    /// Creates a new keypoint with the offset of a page from the start of the chain and the
+    /// numerator of the presentation time of the keyframe.
+    pub fn new(byte_offset: u64, timestamp: i64) -> Self {
+        Self {
+            byte_offset,
+            timestamp,
+        }
+    }
+
+    /// The offset of the page the keyframe starts on, from the start of the chain.
+    pub fn byte_offset(&self) -> u64 {
+        self.byte_offset
+    }
+
+    /// The numerator of the presentation time of the keyframe.
+    pub fn timestamp(&self) -> i64 {
+        self.timestamp
+    }
+}
+
+impl SkeletonIndex {
+    /// Creates a new index of the logical bitstream. The times of the first and last sample
+    /// and the timestamps of the keypoints share the given denominator.
+    ///
+    /// Keypoints need to be ordered by their byte offset and their timestamp.
+    pub fn new(
+        bitstream_serial_number: u32,
+        timestamp_denominator: i64,
+        first_sample_time: i64,
+        last_sample_time: i64,
+        keypoints: Vec<Keypoint>,
+    ) -> Self {
+        Self {
+            bitstream_serial_number,
+            timestamp_denominator,
+            first_sample_time,
+            last_sample_time,
+            keypoints,
+        }
+    }
+
+    /// Parses an index packet.
+    ///
+    /// Returns `None` if the packet is not an index packet or is malformed.
+    pub fn parse(packet: &[u8]) -> Option<Self> {
+        if !packet.starts_with(INDEX_MAGIC) {
+            return None;
+        }
+
+        let keypoint_count = u64::from_le_bytes(read_array(packet, 10)?);
+        let timestamp_denominator = i64::from_le_bytes(read_array(packet, 18)?);
+        if timestamp_denominator <= 0 {
+            return None;
+        }
+
+        // Each keypoint needs at least two bytes.
+        let mut data = packet.get(INDEX_KEYPOINTS_OFFSET..)?;
+        if keypoint_count > u64::try_from(data.len() / 2).ok()? {
+            return None;
+        }
+
+        let mut keypoints = Vec::with_capacity(usize::try_from(keypoint_count).ok()?);
+        let mut byte_offset = 0_u64;
+        let mut timestamp = 0_i64;
+        for _ in 0..keypoint_count {
+            byte_offset = byte_offset.checked_add(read_varint(&mut data)?)?;
+            timestamp = timestamp.checked_add(i64::try_from(read_varint(&mut data)?).ok()?)?;
+            keypoints.push(Keypoint {
+                byte_offset,
+                timestamp,
+            });
+        }
+
+        Some(Self {
+            bitstream_serial_number: u32::from_le_bytes(read_array(packet, 6)?),
+            timestamp_denominator,
+            first_sample_time: i64::from_le_bytes(read_array(packet, 26)?),
+            last_sample_time: i64::from_le_bytes(read_array(packet, 34)?),
+            keypoints,
+        })
+    }
+
+    /// Unique serial ID of the indexed logical bitstream.
+    pub fn bitstream_serial_number(&self) -> u32 {
+        self.bitstream_serial_number
+    }
+
+    /// The denominator of all timestamps of the index.
+    pub fn timestamp_denominator(&self) -> i64 {
+        self.timestamp_denominator
+    }
+
+    /// The numerator of the presentation time of the first sample.
+    pub fn first_sample_time(&self) -> i64 {
+        self.first_sample_time
+    }
+
+    /// The numerator of the presentation time of the end of the last sample.
+    pub fn last_sample_time(&self) -> i64 {
+        self.last_sample_time
+    }
+
+    /// The keypoints, ordered by their byte offset.
+    pub fn keypoints(&self) -> &[Keypoint] {
+        self.keypoints.as_ref()
+    }
+
+    /// Returns the time of the given timestamp numerator.
+    ///
+    /// Returns `None` for negative timestamps.
+    pub fn time(&self, timestamp: i64) -> Option<Duration> {
+        fraction_to_time(
+            u128::try_from(timestamp).ok()?,
+            u128::try_from(self.timestamp_denominator).ok()?,
+        )
+    }
+
+    /// Returns the last keypoint at or before the given time.
+    pub fn keypoint_before(&self, time: Duration) -> Option<&Keypoint> {
+        let target = i128::try_from(time.as_nanos()).ok()? * i128::from(self.timestamp_denominator);
+        self.keypoints
+            .iter()
+            .take_while(|keypoint| {
+                i128::from(keypoint.timestamp) * i128::from(NANOS_PER_SEC) <= target
+            })
+            .last()
+    }
+
+    /// Returns the index packet.
+    ///
+    /// Returns `None` if the keypoints are not ordered.
+    pub fn to_packet(&self) -> Option<Vec<u8>> {
+        let mut packet = Vec::with_capacity(Self::packet_size(self.keypoints.len()));
+        packet.extend_from_slice(INDEX_MAGIC);
+        packet.extend_from_slice(&self.bitstream_serial_number.to_le_bytes());
+        packet.extend_from_slice(&u64::try_from(self.keypoints.len()).ok()?.to_le_bytes());
+        packet.extend_from_slice(&self.timestamp_denominator.to_le_bytes());
+        packet.extend_from_slice(&self.first_sample_time.to_le_bytes());
+        packet.extend_from_slice(&self.last_sample_time.to_le_bytes());
+
+        let mut byte_offset = 0;
+        let mut timestamp = 0;
+        for keypoint in self.keypoints.iter() {
+            write_varint(&mut packet, keypoint.byte_offset.checked_sub(byte_offset)?);
+            let delta = keypoint.timestamp.checked_sub(timestamp)?;
+            write_varint(&mut packet, u64::try_from(delta).ok()?);
+            byte_offset = keypoint.byte_offset;
+            timestamp = keypoint.timestamp;
+        }
+
+        Some(packet)
+    }
+
+    /// The biggest possible size of an index packet with the given amount of keypoints.
+    pub fn packet_size(keypoints: usize) -> usize {
+        INDEX_KEYPOINTS_OFFSET + keypoints * 2 * MAX_VARINT_SIZE
+    }
+}
+
+/// Reads a fixed size array at the given offset.
+fn read_array<const N: usize>(source: &[u8], offset: usize) -> Option<[u8; N]> {
+    source.get(offset..offset.checked_add(N)?)?.try_into().ok()
+}
+
+/// Reads a numerator and denominator at the given offset.
+fn read_rational(source: &[u8], offset: usize) -> Option<(i64, i64)> {
+    Some((
+        i64::from_le_bytes(read_array(source, offset)?),
+        i64::from_le_bytes(read_array(source, offset + 8)?),
+    ))
+}
+
+/// Writes a numerator and denominator.
+fn write_rational(target: &mut Vec<u8>, (numerator, denominator): (i64, i64)) {
+    target.extend_from_slice(&numerator.to_le_bytes());
+    target.extend_from_slice(&denominator.to_le_bytes());
+}
+
+/// Writes a variable length integer of an index packet.
+fn write_varint(target: &mut Vec<u8>, mut value: u64) {
+    while value > 0x7F {
+        target.push(u8::try_from(value & 0x7F).unwrap_or_default());
+        value >>= 7;
+    }
+    target.push(u8::try_from(value).unwrap_or_default() | 0x80);
+}
+
+/// Reads a variable length integer of an index packet.
+///
+/// The integers are stored in groups of 7 bits, starting with the least significant group.
+/// The highest bit marks the last byte. Integers that are longer than 10 bytes or overflow
+/// an `u64` are rejected.
+fn read_varint(data: &mut &[u8]) -> Option<u64> {
+    let mut value = 0_u64;
+    let mut shift = 0_u32;
+
+    loop {
+        let (byte, rest) = data.split_first()?;
+        *data = rest;
+
+        let bits = u64::from(byte & 0x7F);
+        if shift > 63 || (shift == 63 && bits > 1) {
+            return None;
+        }
+        value |= bits << shift;
+        shift += 7;
+
+        if byte & 0x80 != 0 {
+            return Some(value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::panic)]
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+
+    fn fishead_packet() -> Vec<u8> {
+        let mut packet = FISHEAD_MAGIC.to_vec();
+        packet.extend_from_slice(&4_u16.to_le_bytes());
+        packet.extend_from_slice(&0_u16.to_le_bytes());
+        packet.extend_from_slice(&0_i64.to_le_bytes());
+        packet.extend_from_slice(&1000_i64.to_le_bytes());
+        packet.extend_from_slice(&5_i64.to_le_bytes());
+        packet.extend_from_slice(&1000_i64.to_le_bytes());
+        packet.extend_from_slice(b"20240101T000000.000Z");
+        packet.extend_from_slice(&123_456_u64.to_le_bytes());
+        packet.extend_from_slice(&789_u64.to_le_bytes());
+        packet
+    }
+
+    #[test]
+    fn test_fishead() {
+        let fishead = Fishead::parse(&fishead_packet()).unwrap();
+        assert_eq!(fishead.version(), (4, 0));
+        assert_eq!(fishead.presentation_time(), (0, 1000));
+        assert_eq!(fishead.base_time(), (5, 1000));
+        assert_eq!(fishead.utc(), b"20240101T000000.000Z");
+        assert_eq!(fishead.segment_length(), 123_456);
+        assert_eq!(fishead.content_byte_offset(), 789);
+
+        // Version 3 has no segment length and content byte offset.
+        let mut packet = fishead_packet();
+        packet[8] = 3;
+        packet.truncate(FISHEAD_V3_SIZE);
+        let fishead = Fishead::parse(&packet).unwrap();
+        assert_eq!(fishead.version(), (3, 0));
+        assert_eq!(fishead.segment_length(), 0);
+
+        packet[8] = 4;
+        assert!(Fishead::parse(&packet).is_none());
+        assert!(Fishead::parse(b"fisbone\0").is_none());
+    }
+
+    #[test]
+    fn test_fisbone() {
+        let mut packet = FISBONE_MAGIC.to_vec();
+        packet.extend_from_slice(&44_u32.to_le_bytes());
+        packet.extend_from_slice(&0x1234_u32.to_le_bytes());
+        packet.extend_from_slice(&3_u32.to_le_bytes());
+        packet.extend_from_slice(&44_100_i64.to_le_bytes());
+        packet.extend_from_slice(&1_i64.to_le_bytes());
+        packet.extend_from_slice(&0_i64.to_le_bytes());
+        packet.extend_from_slice(&2_u32.to_le_bytes());
+        packet.extend_from_slice(&[0, 0, 0, 0]);
+        packet.extend_from_slice(b"Content-Type: audio/vorbis\r\nRole: audio/main\r\n");
+
+        let fisbone = Fisbone::parse(&packet).unwrap();
+        assert_eq!(fisbone.bitstream_serial_number(), 0x1234);
+        assert_eq!(fisbone.header_packets(), 3);
+        assert_eq!(fisbone.granule_rate(), (44_100, 1));
+        assert_eq!(fisbone.base_granule(), 0);
+        assert_eq!(fisbone.preroll(), 2);
+        assert_eq!(fisbone.granule_shift(), 0);
+        assert_eq!(fisbone.content_type(), Some("audio/vorbis"));
+        assert_eq!(fisbone.message_header("role"), Some("audio/main"));
+        assert_eq!(fisbone.message_headers().len(), 2);
+
+        packet.extend_from_slice(b"invalid\r\n");
+        assert!(Fisbone::parse(&packet).is_none());
+        assert!(Fisbone::parse(&packet[..40]).is_none());
+    }
+
+    #[test]
+    fn test_index() {
+        let mut packet = INDEX_MAGIC.to_vec();
+        packet.extend_from_slice(&7_u32.to_le_bytes());
+        packet.extend_from_slice(&3_u64.to_le_bytes());
+        packet.extend_from_slice(&1000_i64.to_le_bytes());
+        packet.extend_from_slice(&0_i64.to_le_bytes());
+        packet.extend_from_slice(&9000_i64.to_le_bytes());
+        // (500, 0), (500 + 20_000, 2500), (20_500 + 1, 2500 + 4000)
+        packet.extend_from_slice(&[0x74, 0x83, 0x80, 0x20, 0x1C, 0x81, 0x44, 0x93]);
+        packet.extend_from_slice(&[0x81, 0x20, 0x9F]);
+
+        let index = SkeletonIndex::parse(&packet).unwrap();
+        assert_eq!(index.bitstream_serial_number(), 7);
+        assert_eq!(index.timestamp_denominator(), 1000);
+        assert_eq!(index.first_sample_time(), 0);
+        assert_eq!(
+            index.time(index.last_sample_time()),
+            Some(Duration::from_secs(9))
+        );
+
+        let keypoints: Vec<(u64, i64)> = index
+            .keypoints()
+            .iter()
+            .map(|k| (k.byte_offset(), k.timestamp()))
+            .collect();
+        assert_eq!(keypoints, vec![(500, 0), (20_500, 2500), (20_501, 6500)]);
+
+        let keypoint = index.keypoint_before(Duration::from_millis(6499)).unwrap();
+        assert_eq!(keypoint.byte_offset(), 20_500);
+        let keypoint = index.keypoint_before(Duration::from_millis(6500)).unwrap();
+        assert_eq!(keypoint.byte_offset(), 20_501);
+
+        // The keypoint count doesn't match the data.
+        packet[10] = 4;
+        assert!(SkeletonIndex::parse(&packet).is_none());
+    }
+
+    #[test]
+    fn test_to_packet() {
+        let fishead = Fishead::parse(&fishead_packet()).unwrap();
+        assert_eq!(fishead.to_packet(), fishead_packet());
+        assert_eq!(
+            Fishead::new((0, 1), (0, 1)).to_packet().len(),
+            FISHEAD_V4_SIZE
+        );
+
+        let mut fisbone = Fisbone::new(3, "video/theora", (30_000, 1001), 0);
+        fisbone.set_header_packets(3);
+        fisbone.set_granule_shift(6);
+        fisbone.add_message_header("Role", "video/main");
+        assert_eq!(Fisbone::parse(&fisbone.to_packet()), Some(fisbone.clone()));
+        assert_eq!(
+            fisbone.granule_time((60 << 6) | 15),
+            Some((75 * 1001, 30_000))
+        );
+
+        let keypoints = vec![Keypoint::new(500, 0), Keypoint::new(70_000, 2500)];
+        let index = SkeletonIndex::new(3, 1000, 0, 9000, keypoints);
+        let packet = index.to_packet().unwrap();
+        assert!(packet.len() <= SkeletonIndex::packet_size(2));
+        assert_eq!(SkeletonIndex::parse(&packet), Some(index));
+
+        let keypoints = vec![Keypoint::new(500, 10), Keypoint::new(400, 20)];
+        assert!(SkeletonIndex::new(3, 1000, 0, 0, keypoints)
+            .to_packet()
+            .is_none());
+    }
+
+    #[test]
+    fn test_varint() {
+        let mut data: &[u8] = &[0x80, 0x7F, 0xFF, 0x01];
+        assert_eq!(read_varint(&mut data), Some(0));
+        assert_eq!(read_varint(&mut data), Some(0x3FFF));
+        assert_eq!(read_varint(&mut data), None);
+
+        let mut packet = Vec::new();
+        write_varint(&mut packet, 0);
+        write_varint(&mut packet, u64::MAX);
+        assert_eq!(packet.len(), 1 + MAX_VARINT_SIZE);
+        let mut data = packet.as_slice();
+        assert_eq!(read_varint(&mut data), Some(0));
+        assert_eq!(read_varint(&mut data), Some(u64::MAX));
+
+        // The last group of a 10 byte integer only has a single bit left.
+        let mut data: &[u8] = &[0x7F, 0x7F, 0x7F, 0x7F, 0x7F, 0x7F, 0x7F, 0x7F, 0x7F, 0x82];
+        assert_eq!(read_varint(&mut data), None);
+
+        let mut data: &[u8] = &[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x80];
+        assert_eq!(read_varint(&mut data), None);
+    }
+}
@@ -0,0 +1,177 @@
+//! Helpers shared by the unit tests.
+
+#[cfg(feature = "async")]
+pub(crate) use self::async_util::block_on;
+pub(crate) use self::vorbis_packets::{comment_packet, identification_packet, setup_packet};
+
+#[cfg(feature = "async")]
+mod async_util {
+    use std::future::Future;
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Wake, Waker};
+
+    /// Waker that does nothing, the future is polled in a loop anyway.
+    struct NoopWaker;
+
+    impl Wake for NoopWaker {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    /// Polls the future until it is ready.
+    pub(crate) fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = Box::pin(future);
+        let waker = Waker::from(Arc::new(NoopWaker));
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+        }
+    }
+}
+
+mod vorbis_packets {
+    #![allow(clippy::unwrap_used)]
+
+    use std::convert::TryFrom;
+
+    use crate::vorbis::CODEBOOK_SYNC_PATTERN;
+
+    /// Writes bits like they are read by the `BitReader` of the Vorbis headers.
+    #[derive(Default)]
+    struct BitWriter {
+        data: Vec<u8>,
+        position: usize,
+    }
+
+    impl BitWriter {
+        fn write(&mut self, value: u32, bits: u32) {
+            for bit in 0..bits {
+                if self.position % 8 == 0 {
+                    self.data.push(0);
+                }
+                let byte = self.data.last_mut().unwrap();
+                *byte |= u8::try_from((value >> bit) & 0x01).unwrap() << (self.position % 8);
+                self.position += 1;
+            }
+        }
+    }
+
+    /// Returns an identification header of a stereo logical bitstream at 44.1 kHz with
+    /// block sizes of 256 and 2048 samples.
+    pub(crate) fn identification_packet() -> Vec<u8> {
+        let mut packet = b"\x01vorbis".to_vec();
+        packet.extend_from_slice(&0_u32.to_le_bytes());
+        packet.push(2);
+        packet.extend_from_slice(&44_100_u32.to_le_bytes());
+        packet.extend_from_slice(&0_i32.to_le_bytes());
+        packet.extend_from_slice(&128_000_i32.to_le_bytes());
+        packet.extend_from_slice(&(-1_i32).to_le_bytes());
+        packet.push(0xB8);
+        packet.push(0x01);
+        packet
+    }
+
+    /// Returns a comment header with a valid and an invalid comment.
+    pub(crate) fn comment_packet() -> Vec<u8> {
+        let mut packet = b"\x03vorbis".to_vec();
+        packet.extend_from_slice(&4_u32.to_le_bytes());
+        packet.extend_from_slice(b"Xiph");
+        packet.extend_from_slice(&2_u32.to_le_bytes());
+        for comment in [&b"ARTIST=Someone"[..], b"invalid"].iter() {
+            packet.extend_from_slice(&u32::try_from(comment.len()).unwrap().to_le_bytes());
+            packet.extend_from_slice(comment);
+        }
+        packet.push(0x01);
+        packet
+    }
+
+    /// Returns a setup header with two modes, one for short and one for long blocks.
+    pub(crate) fn setup_packet() -> Vec<u8> {
+        let mut writer = BitWriter::default();
+
+        // Two codebooks.
+        writer.write(1, 8);
+        // A codebook with two unordered entries of one dimension.
+        writer.write(CODEBOOK_SYNC_PATTERN, 24);
+        writer.write(1, 16);
+        writer.write(2, 24);
+        writer.write(0, 1);
+        writer.write(0, 1);
+        writer.write(0, 5);
+        writer.write(0, 5);
+        writer.write(0, 4);
+        // A codebook with nine ordered entries of two dimensions and a lookup table.
+        writer.write(CODEBOOK_SYNC_PATTERN, 24);
+        writer.write(2, 16);
+        writer.write(9, 24);
+        writer.write(1, 1);
+        writer.write(3, 5);
+        writer.write(9, 4);
+        writer.write(1, 4);
+        writer.write(0, 32);
+        writer.write(0, 32);
+        writer.write(3, 4);
+        writer.write(0, 1);
+        // Three lookup values of four bits.
+        writer.write(0xFFF, 12);
+
+        // One time domain transform.
+        writer.write(0, 6);
+        writer.write(0, 16);
+
+        // A floor of type 1 with one partition.
+        writer.write(0, 6);
+        writer.write(1, 16);
+        writer.write(1, 5);
+        writer.write(0, 4);
+        writer.write(1, 3);
+        writer.write(1, 2);
+        writer.write(0, 8);
+        writer.write(0, 8);
+        writer.write(1, 8);
+        writer.write(0, 2);
+        writer.write(7, 4);
+        writer.write(0x3FFF, 14);
+
+        // A residue of type 2 with one classification.
+        writer.write(0, 6);
+        writer.write(2, 16);
+        writer.write(0, 24);
+        writer.write(0, 24);
+        writer.write(31, 24);
+        writer.write(0, 6);
+        writer.write(0, 8);
+        writer.write(0b101, 3);
+        writer.write(0, 1);
+        writer.write(0, 8);
+        writer.write(1, 8);
+
+        // A mapping with one submap and one coupling step.
+        writer.write(0, 6);
+        writer.write(0, 16);
+        writer.write(0, 1);
+        writer.write(1, 1);
+        writer.write(0, 8);
+        writer.write(0, 1);
+        writer.write(1, 1);
+        writer.write(0, 2);
+        writer.write(0, 24);
+
+        // Two modes.
+        writer.write(1, 6);
+        for block_flag in [0, 1].iter() {
+            writer.write(*block_flag, 1);
+            writer.write(0, 16);
+            writer.write(0, 16);
+            writer.write(0, 8);
+        }
+
+        // The framing bit.
+        writer.write(1, 1);
+
+        let mut packet = b"\x05vorbis".to_vec();
+        packet.extend_from_slice(&writer.data);
+        packet
+    }
+}
@@ -0,0 +1,52 @@
+//! Compatibility with the I/O traits of Tokio.
+
+use std::io::Result;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// Wraps a Tokio reader or writer, so that it can be used with the async reader or writer.
+#[derive(Clone, Debug)]
+pub struct TokioCompat<T> {
+    inner: T,
+}
+
+impl<T> TokioCompat<T> {
+    /// Creates a new `TokioCompat`.
+    pub fn new(inner: T) -> Self {
+        Self { inner }
+    }
+
+    /// Consumes the `TokioCompat` and returns the wrapped reader or writer.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T: tokio::io::AsyncRead + Unpin> futures_io::AsyncRead for TokioCompat<T> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize>> {
+        let mut read_buf = tokio::io::ReadBuf::new(buf);
+        match Pin::new(&mut self.get_mut().inner).poll_read(cx, &mut read_buf) {
+            Poll::Ready(Ok(())) => Poll::Ready(Ok(read_buf.filled().len())),
+            Poll::Ready(Err(err)) => Poll::Ready(Err(err)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<T: tokio::io::AsyncWrite + Unpin> futures_io::AsyncWrite for TokioCompat<T> {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<Result<usize>> {
+        Pin::new(&mut self.get_mut().inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
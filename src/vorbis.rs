@@ -0,0 +1,551 @@
+//! Parsing of the Vorbis I headers.
+
+use std::convert::{TryFrom, TryInto};
+
+use crate::comment::{find_comment, parse_comment_list};
+
+/// The magic bytes that follow the packet type of a header.
+const VORBIS_MAGIC: &[u8] = b"vorbis";
+
+/// The packet types of the headers.
+const IDENTIFICATION_HEADER_TYPE: u8 = 1;
+const COMMENT_HEADER_TYPE: u8 = 3;
+const SETUP_HEADER_TYPE: u8 = 5;
+
+/// The size of the identification header.
+const IDENTIFICATION_HEADER_SIZE: usize = 30;
+
+/// The sync pattern in front of each codebook.
+pub(crate) const CODEBOOK_SYNC_PATTERN: u32 = 0x56_43_42;
+
+/// The identification header of a Vorbis logical bitstream, which is its BOS packet.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct VorbisIdentification {
+    channel_count: u8,
+    sample_rate: u32,
+    bitrate_maximum: i32,
+    bitrate_nominal: i32,
+    bitrate_minimum: i32,
+    block_sizes: [u16; 2],
+}
+
+impl VorbisIdentification {
+    /// Parses an identification header.
+    ///
+    /// Returns `None` if the packet is not an identification header of Vorbis I or if it is
+    /// invalid.
+    pub fn parse(packet: &[u8]) -> Option<Self> {
+        let data = strip_header_prefix(packet, IDENTIFICATION_HEADER_TYPE)?;
+        if packet.len() < IDENTIFICATION_HEADER_SIZE {
+            return None;
+        }
+
+        let version = u32::from_le_bytes(data[..4].try_into().ok()?);
+        let channel_count = data[4];
+        let sample_rate = u32::from_le_bytes(data[5..9].try_into().ok()?);
+        if version != 0 || channel_count == 0 || sample_rate == 0 {
+            return None;
+        }
+
+        // Both block sizes are powers of two between 64 and 8192.
+        let exponents = [data[21] & 0x0F, data[21] >> 4];
+        if exponents.iter().any(|e| !(6..=13).contains(e)) || exponents[0] > exponents[1] {
+            return None;
+        }
+
+        // The framing bit must be set.
+        if data[22] & 0x01 == 0 {
+            return None;
+        }
+
+        Some(Self {
+            channel_count,
+            sample_rate,
+            bitrate_maximum: i32::from_le_bytes(data[9..13].try_into().ok()?),
+            bitrate_nominal: i32::from_le_bytes(data[13..17].try_into().ok()?),
+            bitrate_minimum: i32::from_le_bytes(data[17..21].try_into().ok()?),
+            block_sizes: [1 << exponents[0], 1 << exponents[1]],
+        })
+    }
+
+    /// The number of audio channels.
+    pub fn channel_count(&self) -> u8 {
+        self.channel_count
+    }
+
+    /// The sample rate of the audio in Hz, which is the rate of the granule positions.
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// The maximum bitrate in bit/s, if set.
+    pub fn bitrate_maximum(&self) -> Option<u32> {
+        positive(self.bitrate_maximum)
+    }
+
+    /// The nominal bitrate in bit/s, if set.
+    pub fn bitrate_nominal(&self) -> Option<u32> {
+        positive(self.bitrate_nominal)
+    }
+
+    /// The minimum bitrate in bit/s, if set.
+    pub fn bitrate_minimum(&self) -> Option<u32> {
+        positive(self.bitrate_minimum)
+    }
+
+    /// The size of the short blocks in samples.
+    pub fn short_block_size(&self) -> u16 {
+        self.block_sizes[0]
+    }
+
+    /// The size of the long blocks in samples.
+    pub fn long_block_size(&self) -> u16 {
+        self.block_sizes[1]
+    }
+}
+
+/// The comment header of a Vorbis logical bitstream.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct VorbisComment {
+    vendor: String,
+    comments: Vec<(String, String)>,
+}
+
+impl VorbisComment {
+    /// Parses a comment header.
+    ///
+    /// Returns `None` if the packet is not a comment header, is truncated or misses the
+    /// framing bit. User comments without a `=` are ignored.
+    pub fn parse(packet: &[u8]) -> Option<Self> {
+        let data = strip_header_prefix(packet, COMMENT_HEADER_TYPE)?;
+        let (vendor, comments, data) = parse_comment_list(data)?;
+
+        if data.first()? & 0x01 == 0 {
+            return None;
+        }
+
+        Some(Self { vendor, comments })
+    }
+
+    /// The vendor string of the encoder.
+    pub fn vendor(&self) -> &str {
+        self.vendor.as_str()
+    }
+
+    /// The user comments as tag and value pairs, like `ARTIST` and its name.
+    pub fn comments(&self) -> &[(String, String)] {
+        self.comments.as_ref()
+    }
+
+    /// Returns the value of the first user comment with the given tag. Tags are compared
+    /// case insensitive.
+    pub fn comment(&self, tag: &str) -> Option<&str> {
+        find_comment(&self.comments, tag)
+    }
+}
+
+/// The setup header of a Vorbis logical bitstream.
+///
+/// Only the mode table is kept, which is needed to compute the block size of the audio
+/// packets, and with it the number of samples each packet adds to the granule position.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct VorbisSetup {
+    block_sizes: [u16; 2],
+    /// The block flag of each mode.
+    mode_block_flags: Vec<bool>,
+}
+
+impl VorbisSetup {
+    /// Parses a setup header with the identification header of its logical bitstream.
+    ///
+    /// The codebooks, floors, residues and mappings are validated as far as needed to find
+    /// the mode table. Returns `None` if the packet is not a setup header or if it is
+    /// invalid.
+    pub fn parse(packet: &[u8], identification: &VorbisIdentification) -> Option<Self> {
+        let mut reader = BitReader::new(strip_header_prefix(packet, SETUP_HEADER_TYPE)?);
+
+        for _ in 0..=reader.read(8)? {
+            skip_codebook(&mut reader)?;
+        }
+
+        // The time domain transforms are placeholders.
+        for _ in 0..=reader.read(6)? {
+            if reader.read(16)? != 0 {
+                return None;
+            }
+        }
+
+        for _ in 0..=reader.read(6)? {
+            skip_floor(&mut reader)?;
+        }
+
+        for _ in 0..=reader.read(6)? {
+            skip_residue(&mut reader)?;
+        }
+
+        let mapping_count = reader.read(6)? + 1;
+        for _ in 0..mapping_count {
+            skip_mapping(&mut reader, identification.channel_count())?;
+        }
+
+        let mode_count = reader.read(6)? + 1;
+        let mut mode_block_flags = Vec::with_capacity(usize::try_from(mode_count).ok()?);
+        for _ in 0..mode_count {
+            let block_flag = reader.read(1)? == 1;
+            let window_type = reader.read(16)?;
+            let transform_type = reader.read(16)?;
+            let mapping = reader.read(8)?;
+            if window_type != 0 || transform_type != 0 || mapping >= mapping_count {
+                return None;
+            }
+            mode_block_flags.push(block_flag);
+        }
+
+        if reader.read(1)? != 1 {
+            return None;
+        }
+
+        Some(Self {
+            block_sizes: identification.block_sizes,
+            mode_block_flags,
+        })
+    }
+
+    /// The number of modes.
+    pub fn mode_count(&self) -> usize {
+        self.mode_block_flags.len()
+    }
+
+    /// Returns the block size of an audio packet in samples.
+    ///
+    /// Returns `None` if the packet is not an audio packet or uses an unknown mode.
+    pub fn block_size(&self, packet: &[u8]) -> Option<u16> {
+        let mut reader = BitReader::new(packet);
+        if reader.read(1)? != 0 {
+            return None;
+        }
+
+        let mode_bits = ilog(u32::try_from(self.mode_count() - 1).ok()?);
+        let mode = reader.read(mode_bits)?;
+        let block_flag = *self.mode_block_flags.get(usize::try_from(mode).ok()?)?;
+
+        Some(self.block_sizes[usize::from(block_flag)])
+    }
+
+    /// Returns the number of samples that decoding the audio packet after the previous audio
+    /// packet adds to the granule position, which is a quarter of both block sizes.
+    ///
+    /// The first audio packet of a logical bitstream only primes the decoder and adds no
+    /// samples. Returns `None` if a packet is not an audio packet or uses an unknown mode.
+    pub fn sample_count(&self, previous_packet: &[u8], packet: &[u8]) -> Option<u32> {
+        let previous_block_size = self.block_size(previous_packet)?;
+        let block_size = self.block_size(packet)?;
+
+        Some(Self::block_sample_count(previous_block_size, block_size))
+    }
+
+    /// Returns the number of samples that decoding a block after a block of the previous
+    /// block size adds to the granule position, which is a quarter of both block sizes.
+    pub fn block_sample_count(previous_block_size: u16, block_size: u16) -> u32 {
+        u32::from(previous_block_size / 4) + u32::from(block_size / 4)
+    }
+}
+
+/// Returns the data after the packet type and magic bytes of a header.
+fn strip_header_prefix(packet: &[u8], header_type: u8) -> Option<&[u8]> {
+    packet
+        .strip_prefix(&[header_type])?
+        .strip_prefix(VORBIS_MAGIC)
+}
+
+/// Returns the value if it is positive.
+fn positive(value: i32) -> Option<u32> {
+    u32::try_from(value).ok().filter(|value| *value != 0)
+}
+
+/// Returns the number of bits needed to represent the value.
+fn ilog(value: u32) -> u32 {
+    32 - value.leading_zeros()
+}
+
+/// Skips a codebook.
+fn skip_codebook(reader: &mut BitReader<'_>) -> Option<()> {
+    if reader.read(24)? != CODEBOOK_SYNC_PATTERN {
+        return None;
+    }
+
+    let dimensions = reader.read(16)?;
+    let entries = reader.read(24)?;
+
+    // The codeword lengths.
+    if reader.read(1)? == 1 {
+        let mut current_entry = 0;
+        reader.read(5)?;
+        while current_entry < entries {
+            current_entry += reader.read(ilog(entries - current_entry))?;
+        }
+        if current_entry > entries {
+            return None;
+        }
+    } else if reader.read(1)? == 1 {
+        for _ in 0..entries {
+            if reader.read(1)? == 1 {
+                reader.read(5)?;
+            }
+        }
+    } else {
+        reader.skip(u64::from(entries) * 5)?;
+    }
+
+    let lookup_values = match reader.read(4)? {
+        0 => return Some(()),
+        1 => lookup1_values(entries, dimensions),
+        2 => u64::from(entries) * u64::from(dimensions),
+        _ => return None,
+    };
+
+    // The minimum value and the delta value.
+    reader.skip(64)?;
+    let value_bits = reader.read(4)? + 1;
+    reader.read(1)?;
+    reader.skip(lookup_values.checked_mul(u64::from(value_bits))?)
+}
+
+/// Returns the greatest value whose power of the dimensions doesn't exceed the entries.
+fn lookup1_values(entries: u32, dimensions: u32) -> u64 {
+    let fits = |value: u64| {
+        value
+            .checked_pow(dimensions)
+            .map_or(false, |power| power <= u64::from(entries))
+    };
+
+    let (mut low, mut high) = (0, u64::from(entries) + 1);
+    while high - low > 1 {
+        let middle = low + (high - low) / 2;
+        if fits(middle) {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    low
+}
+
+/// Skips a floor configuration.
+fn skip_floor(reader: &mut BitReader<'_>) -> Option<()> {
+    match reader.read(16)? {
+        0 => {
+            // The order, rate, bark map size, amplitude bits and amplitude offset.
+            reader.skip(8 + 16 + 16 + 6 + 8)?;
+            let book_count = reader.read(4)? + 1;
+            reader.skip(u64::from(book_count) * 8)
+        }
+        1 => {
+            let partitions = reader.read(5)?;
+            let mut partition_classes = Vec::with_capacity(usize::try_from(partitions).ok()?);
+            for _ in 0..partitions {
+                partition_classes.push(reader.read(4)?);
+            }
+
+            let class_count = partition_classes.iter().max().map_or(0, |class| class + 1);
+            let mut class_dimensions = Vec::with_capacity(usize::try_from(class_count).ok()?);
+            for _ in 0..class_count {
+                class_dimensions.push(reader.read(3)? + 1);
+                let subclasses = reader.read(2)?;
+                if subclasses != 0 {
+                    reader.read(8)?;
+                }
+                reader.skip(8_u64 << subclasses)?;
+            }
+
+            // The multiplier.
+            reader.read(2)?;
+            let range_bits = reader.read(4)?;
+            for class in partition_classes.iter() {
+                let dimensions = class_dimensions.get(usize::try_from(*class).ok()?)?;
+                reader.skip(u64::from(*dimensions) * u64::from(range_bits))?;
+            }
+            Some(())
+        }
+        _ => None,
+    }
+}
+
+/// Skips a residue configuration.
+fn skip_residue(reader: &mut BitReader<'_>) -> Option<()> {
+    if reader.read(16)? > 2 {
+        return None;
+    }
+
+    // The begin, end and partition size.
+    reader.skip(24 * 3)?;
+    let classifications = reader.read(6)? + 1;
+    // The classbook.
+    reader.read(8)?;
+
+    let mut book_count = 0;
+    for _ in 0..classifications {
+        let mut cascade = reader.read(3)?;
+        if reader.read(1)? == 1 {
+            cascade |= reader.read(5)? << 3;
+        }
+        book_count += cascade.count_ones();
+    }
+
+    reader.skip(u64::from(book_count) * 8)
+}
+
+/// Skips a mapping configuration.
+fn skip_mapping(reader: &mut BitReader<'_>, channel_count: u8) -> Option<()> {
+    if reader.read(16)? != 0 {
+        return None;
+    }
+
+    let submaps = if reader.read(1)? == 1 {
+        reader.read(4)? + 1
+    } else {
+        1
+    };
+
+    if reader.read(1)? == 1 {
+        let channel_bits = ilog(u32::from(channel_count) - 1);
+        for _ in 0..=reader.read(8)? {
+            let magnitude = reader.read(channel_bits)?;
+            let angle = reader.read(channel_bits)?;
+            let channel_count = u32::from(channel_count);
+            if magnitude == angle || magnitude >= channel_count || angle >= channel_count {
+                return None;
+            }
+        }
+    }
+
+    if reader.read(2)? != 0 {
+        return None;
+    }
+
+    if submaps > 1 {
+        for _ in 0..channel_count {
+            if reader.read(4)? >= submaps {
+                return None;
+            }
+        }
+    }
+
+    // The time configuration, floor and residue of each submap.
+    reader.skip(u64::from(submaps) * 8 * 3)
+}
+
+/// Reads the bits of a packet, starting with the least significant bit of each byte.
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: u64,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    /// Reads up to 32 bits as an unsigned integer. Returns `None` at the end of the data.
+    fn read(&mut self, bits: u32) -> Option<u32> {
+        let mut value = 0;
+        for bit in 0..bits {
+            let byte = self.data.get(usize::try_from(self.position / 8).ok()?)?;
+            value |= u32::from((byte >> (self.position % 8)) & 0x01) << bit;
+            self.position += 1;
+        }
+        Some(value)
+    }
+
+    /// Skips the given number of bits. Returns `None` if the data is shorter.
+    fn skip(&mut self, bits: u64) -> Option<()> {
+        let position = self.position.checked_add(bits)?;
+        if position > u64::try_from(self.data.len()).ok()?.checked_mul(8)? {
+            return None;
+        }
+        self.position = position;
+        Some(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::panic)]
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+    use crate::test_util::{comment_packet, identification_packet, setup_packet};
+
+    #[test]
+    fn test_identification() {
+        let packet = identification_packet();
+        let identification = VorbisIdentification::parse(&packet).unwrap();
+        assert_eq!(identification.channel_count(), 2);
+        assert_eq!(identification.sample_rate(), 44_100);
+        assert_eq!(identification.bitrate_maximum(), None);
+        assert_eq!(identification.bitrate_nominal(), Some(128_000));
+        assert_eq!(identification.bitrate_minimum(), None);
+        assert_eq!(identification.short_block_size(), 256);
+        assert_eq!(identification.long_block_size(), 2048);
+
+        // The short blocks are longer than the long blocks.
+        let mut invalid = packet.clone();
+        invalid[28] = 0x8B;
+        assert!(VorbisIdentification::parse(&invalid).is_none());
+
+        // The framing bit is missing.
+        let mut invalid = packet.clone();
+        invalid[29] = 0;
+        assert!(VorbisIdentification::parse(&invalid).is_none());
+
+        assert!(VorbisIdentification::parse(&packet[..29]).is_none());
+        assert!(VorbisIdentification::parse(b"\x03vorbis").is_none());
+    }
+
+    #[test]
+    fn test_comment() {
+        let packet = comment_packet();
+        let comment = VorbisComment::parse(&packet).unwrap();
+        assert_eq!(comment.vendor(), "Xiph");
+        assert_eq!(comment.comments().len(), 1);
+        assert_eq!(comment.comment("artist"), Some("Someone"));
+
+        // The framing bit is missing.
+        assert!(VorbisComment::parse(&packet[..packet.len() - 1]).is_none());
+    }
+
+    #[test]
+    fn test_setup() {
+        let identification = VorbisIdentification::parse(&identification_packet()).unwrap();
+        let packet = setup_packet();
+
+        let setup = VorbisSetup::parse(&packet, &identification).unwrap();
+        assert_eq!(setup.mode_count(), 2);
+
+        // The packet type bit is followed by one bit for the mode.
+        assert_eq!(setup.block_size(&[0b00]), Some(256));
+        assert_eq!(setup.block_size(&[0b10]), Some(2048));
+        assert_eq!(setup.block_size(&[0b01]), None);
+        assert_eq!(setup.block_size(&[]), None);
+
+        assert_eq!(setup.sample_count(&[0b00], &[0b10]), Some(64 + 512));
+        assert_eq!(setup.sample_count(&[0b10], &[0b10]), Some(1024));
+        assert_eq!(VorbisSetup::block_sample_count(2048, 256), 512 + 64);
+
+        assert!(VorbisSetup::parse(&packet[..packet.len() - 1], &identification).is_none());
+
+        // The mapping couples a channel that doesn't exist.
+        let mut mono = identification_packet();
+        mono[11] = 1;
+        let mono = VorbisIdentification::parse(&mono).unwrap();
+        assert!(VorbisSetup::parse(&packet, &mono).is_none());
+    }
+
+    #[test]
+    fn test_lookup1_values() {
+        assert_eq!(lookup1_values(9, 2), 3);
+        assert_eq!(lookup1_values(8, 2), 2);
+        assert_eq!(lookup1_values(100, 1), 100);
+        assert_eq!(lookup1_values(0, 3), 0);
+    }
+}
@@ -15,6 +15,14 @@ pub enum WriteError {
     BitstreamAlreadyInitialized,
     /// Initial packet too big.
     InitialPacketTooBig,
+    /// Segment table of the page doesn't match its payload.
+    InvalidPage,
+    /// The header packets of the chain are already finished.
+    HeadersFinished,
+    /// The packet is invalid for the codec of the logical bitstream.
+    InvalidPacket,
+    /// The end trim exceeds the duration of the last packet.
+    InvalidEndTrim,
 }
 
 impl std::fmt::Display for WriteError {
@@ -33,7 +41,22 @@ impl std::fmt::Display for WriteError {
                 write!(f, "logical bitstream already initialized")
             }
             WriteError::InitialPacketTooBig => {
-                write!(f, "initial packet too big. Max size: 65_025 byte")
+                write!(f, "initial packet too big. Max size: 65_024 byte")
+            }
+            WriteError::InvalidPage => {
+                write!(f, "segment table of the page doesn't match its payload")
+            }
+            WriteError::HeadersFinished => {
+                write!(f, "the header packets of the chain are already finished")
+            }
+            WriteError::InvalidPacket => {
+                write!(
+                    f,
+                    "the packet is invalid for the codec of the logical bitstream"
+                )
+            }
+            WriteError::InvalidEndTrim => {
+                write!(f, "the end trim exceeds the duration of the last packet")
             }
         }
     }
@@ -3,18 +3,30 @@ use std::io::Write;
 
 use crate::crc32::crc32;
 use crate::{
-    WriteError, BITSTREAM_SERIAL_NUMBER_RANGE, BOS_VALUE, CONTINUATION_VALUE, CRC32_RANGE,
+    Page, WriteError, BITSTREAM_SERIAL_NUMBER_RANGE, BOS_VALUE, CONTINUATION_VALUE, CRC32_RANGE,
     EOS_VALUE, GRANULE_POSITION_RANGE, HEADER_TYPE_INDEX, MAX_PAGE_DATA_SIZE, MAX_PAGE_SIZE,
     PAGER_MARKER, PAGER_MARKER_RANGE, PAGE_SEQUENCE_NUMBER_RANGE, SEGMENT_COUNT_INDEX,
     SEGMENT_TABLE_INDEX,
 };
 
+#[cfg(feature = "async")]
+mod async_writer;
+mod opus;
+mod skeleton;
+mod vorbis;
+
+#[cfg(feature = "async")]
+pub use async_writer::AsyncStreamWriter;
+pub use opus::OpusWriter;
+pub use skeleton::SkeletonWriter;
+pub use vorbis::VorbisWriter;
+
 #[derive(Clone, Debug)]
 struct StreamState {
     bitstream_serial_number: u32,
     data_buffer: Box<[u8]>,
     data_head: usize,
-    packet_sizes: Vec<usize>,
+    segment_table: Vec<u8>,
     page_sequence_number: u32,
     granule_position: u64,
     header_type: u8,
@@ -26,7 +38,7 @@ impl Default for StreamState {
             bitstream_serial_number: 0,
             data_buffer: vec![0_u8; MAX_PAGE_DATA_SIZE].into_boxed_slice(),
             data_head: 0,
-            packet_sizes: Vec::with_capacity(16),
+            segment_table: Vec::with_capacity(255),
             page_sequence_number: 0,
             granule_position: 0,
             header_type: 0,
@@ -37,21 +49,16 @@ impl Default for StreamState {
 /// Generic OGG stream writer.
 #[derive(Clone, Debug)]
 pub struct StreamWriter<W: Write> {
+    inner: BitStreamWriter,
     writer: W,
-    stream_states: Vec<StreamState>,
-    page_buffer: Box<[u8]>,
 }
 
 impl<W: Write> StreamWriter<W> {
     /// Creates a new `StreamWriter`.
     pub fn new(writer: W) -> Self {
-        let mut page_buffer = vec![0_u8; MAX_PAGE_SIZE];
-        page_buffer[PAGER_MARKER_RANGE].copy_from_slice(&PAGER_MARKER);
-
         Self {
+            inner: Default::default(),
             writer,
-            stream_states: Default::default(),
-            page_buffer: page_buffer.into_boxed_slice(),
         }
     }
 
@@ -66,6 +73,108 @@ impl<W: Write> StreamWriter<W> {
         &mut self,
         bitstream_serial_number: u32,
         first_packet_data: &[u8],
+    ) -> Result<(), WriteError> {
+        self.inner
+            .begin_logical_stream(bitstream_serial_number, first_packet_data)?;
+        self.write_pages()
+    }
+
+    /// Ends the logical stream. Caller needs to provide the last packet, which will be
+    /// written by the writer right away. Any open pages for this stream will be flushed.
+    pub fn end_logical_stream(
+        &mut self,
+        bitstream_serial_number: u32,
+        last_packet_data: &[u8],
+        granule_position: u64,
+    ) -> Result<(), WriteError> {
+        self.inner.end_logical_stream(
+            bitstream_serial_number,
+            last_packet_data,
+            granule_position,
+        )?;
+        self.write_pages()
+    }
+
+    /// Queues the the given data as a packet to be written to the writer for the specified
+    /// logical bitstream. Caller need to begin a stream with `begin_logical_stream` and
+    /// close it with `end_logical_stream()`.
+    ///
+    /// Packets are assembles in pages, which are written once a packet doesn't fit into it's
+    /// free space or `flush()` was called manually.
+    ///
+    /// Packets will be split into multiple pages if they are bigger than the biggest allowed
+    /// data page size of 65_025 B.
+    pub fn push_packet(
+        &mut self,
+        bitstream_serial_number: u32,
+        packet_data: &[u8],
+        granule_position: u64,
+    ) -> Result<(), WriteError> {
+        self.inner
+            .push_packet(bitstream_serial_number, packet_data, granule_position)?;
+        self.write_pages()
+    }
+
+    /// The current page of the logical bitstream is written and a new page is started.
+    pub fn flush(&mut self, bitstream_serial_number: u32) -> Result<(), WriteError> {
+        self.inner.flush(bitstream_serial_number)?;
+        self.write_pages()
+    }
+
+    /// Writes the given page verbatim to the writer. The CRC32 checksum is recalculated, so
+    /// the bitstream serial number, page sequence number or granule position of the page can
+    /// be rewritten beforehand.
+    ///
+    /// The page is written independently of the logical streams started with
+    /// `begin_logical_stream()`, which is why its bitstream serial number must not belong
+    /// to one of them.
+    pub fn write_page(&mut self, page: &Page) -> Result<(), WriteError> {
+        self.inner.write_page(page)?;
+        self.write_pages()
+    }
+
+    /// Returns true if the current page for the given logical bitstream contains no data.
+    pub fn page_is_empty(&mut self, bitstream_serial_number: u32) -> Result<bool, WriteError> {
+        self.inner.page_is_empty(bitstream_serial_number)
+    }
+
+    /// Writes the assembled pages to the writer.
+    fn write_pages(&mut self) -> Result<(), WriteError> {
+        let result = self.writer.write_all(&self.inner.pages);
+        self.inner.pages.clear();
+        result?;
+
+        Ok(())
+    }
+}
+
+/// Assembles the pages of the logical bitstreams. The assembled pages are collected until
+/// they are written by the actual writer.
+#[derive(Clone, Debug)]
+struct BitStreamWriter {
+    stream_states: Vec<StreamState>,
+    page_buffer: Box<[u8]>,
+    pages: Vec<u8>,
+}
+
+impl Default for BitStreamWriter {
+    fn default() -> Self {
+        let mut page_buffer = vec![0_u8; MAX_PAGE_SIZE];
+        page_buffer[PAGER_MARKER_RANGE].copy_from_slice(&PAGER_MARKER);
+
+        Self {
+            stream_states: Default::default(),
+            page_buffer: page_buffer.into_boxed_slice(),
+            pages: Vec::with_capacity(MAX_PAGE_SIZE),
+        }
+    }
+}
+
+impl BitStreamWriter {
+    fn begin_logical_stream(
+        &mut self,
+        bitstream_serial_number: u32,
+        first_packet_data: &[u8],
     ) -> Result<(), WriteError> {
         if self
             .stream_states
@@ -75,7 +184,8 @@ impl<W: Write> StreamWriter<W> {
             return Err(WriteError::BitstreamAlreadyInitialized);
         }
 
-        if first_packet_data.len() > MAX_PAGE_DATA_SIZE {
+        // The packet needs to end on the BOS page.
+        if first_packet_data.len() >= MAX_PAGE_DATA_SIZE {
             return Err(WriteError::InitialPacketTooBig);
         }
 
@@ -85,18 +195,15 @@ impl<W: Write> StreamWriter<W> {
         };
 
         state.header_type = BOS_VALUE;
-        push_packet(&mut state, &first_packet_data);
-        write_page(&mut self.writer, &mut state, &mut self.page_buffer)?;
-        state.header_type = 0x0;
+        push_segments(&mut state, first_packet_data, true);
+        write_page(&mut self.pages, &mut state, &mut self.page_buffer)?;
 
         self.stream_states.push(state);
 
         Ok(())
     }
 
-    /// Ends the logical stream. Caller needs to provide the last packet, which will be
-    /// written by the writer right away. Any open pages for this stream will be flushed.
-    pub fn end_logical_stream(
+    fn end_logical_stream(
         &mut self,
         bitstream_serial_number: u32,
         last_packet_data: &[u8],
@@ -112,28 +219,24 @@ impl<W: Write> StreamWriter<W> {
 
         let mut state = self.stream_states.remove(index);
 
-        if state.data_head != 0 {
-            write_page(&mut self.writer, &mut state, &mut self.page_buffer)?;
+        if !state.segment_table.is_empty() {
+            write_page(&mut self.pages, &mut state, &mut self.page_buffer)?;
         }
 
-        state.header_type = EOS_VALUE;
-        state.granule_position = granule_position;
-        push_packet(&mut state, &last_packet_data);
-        write_page(&mut self.writer, &mut state, &mut self.page_buffer)?;
+        queue_packet(
+            &mut self.pages,
+            &mut state,
+            &mut self.page_buffer,
+            last_packet_data,
+            granule_position,
+        )?;
+        state.header_type |= EOS_VALUE;
+        write_page(&mut self.pages, &mut state, &mut self.page_buffer)?;
 
         Ok(())
     }
 
-    /// Queues the the given data as a packet to be written to the writer for the specified
-    /// logical bitstream. Caller need to begin a stream with `begin_logical_stream` and
-    /// close it with `end_logical_stream()`.
-    ///
-    /// Packets are assembles in pages, which are written once a packet doesn't fit into it's
-    /// free space or `flush()` was called manually.
-    ///
-    /// Packets will be split into multiple pages if they are bigger than the biggest allowed
-    /// data page size of 65_025 B.
-    pub fn push_packet(
+    fn push_packet(
         &mut self,
         bitstream_serial_number: u32,
         packet_data: &[u8],
@@ -145,90 +248,121 @@ impl<W: Write> StreamWriter<W> {
             .find(|s| s.bitstream_serial_number == bitstream_serial_number)
             .ok_or(WriteError::UnknownBitstreamSerialNumber)?;
 
-        let mut size = packet_data.len();
-
-        // Flush page if the new data doesn't fit into the free space.
-        if state.data_head != 0 && state.data_head + size > MAX_PAGE_DATA_SIZE {
-            write_page(&mut self.writer, state, &mut self.page_buffer)?;
+        // Flush page if the new data doesn't fit into the free segments.
+        let segments = packet_data.len() / 255 + 1;
+        if !state.segment_table.is_empty() && state.segment_table.len() + segments > 255 {
+            write_page(&mut self.pages, state, &mut self.page_buffer)?;
         }
 
-        // If the data then fits on the page, we safe it and return.
-        if state.data_head + size <= MAX_PAGE_DATA_SIZE {
-            state.granule_position = granule_position;
-            push_packet(state, packet_data);
-
-            if state.data_head == MAX_PAGE_DATA_SIZE {
-                write_page(&mut self.writer, state, &mut self.page_buffer)?;
-            }
-
-            return Ok(());
-        }
-
-        // The data even after flushing is bigger than a page,
-        // so we will split it into multiple pages.
-        let mut is_first_page = true;
-        let mut offset = 0;
-        loop {
-            if is_first_page {
-                is_first_page = false;
-                state.header_type = 0x0;
-            } else {
-                state.header_type = CONTINUATION_VALUE;
-            }
-
-            // Specification said that only the last page should have the proper granule position set.
-            if size <= MAX_PAGE_DATA_SIZE {
-                state.granule_position = granule_position;
-                push_packet(state, &packet_data[offset..offset + size]);
-                write_page(&mut self.writer, state, &mut self.page_buffer)?;
-                break;
-            } else {
-                state.granule_position = u64::MAX;
-                push_packet(state, &packet_data[offset..offset + MAX_PAGE_DATA_SIZE]);
-                write_page(&mut self.writer, state, &mut self.page_buffer)?;
-                offset += MAX_PAGE_DATA_SIZE;
-                size -= MAX_PAGE_DATA_SIZE;
-            }
+        let is_split = queue_packet(
+            &mut self.pages,
+            state,
+            &mut self.page_buffer,
+            packet_data,
+            granule_position,
+        )?;
+
+        // Packets that span multiple pages end in their own page.
+        if is_split || state.segment_table.len() == 255 {
+            write_page(&mut self.pages, state, &mut self.page_buffer)?;
         }
 
-        state.header_type = 0x0;
-
         Ok(())
     }
 
-    /// The current page of the logical bitstream is written and a new page is started.
-    pub fn flush(&mut self, bitstream_serial_number: u32) -> Result<(), WriteError> {
+    fn flush(&mut self, bitstream_serial_number: u32) -> Result<(), WriteError> {
         let state = self
             .stream_states
             .iter_mut()
             .find(|s| s.bitstream_serial_number == bitstream_serial_number)
             .ok_or(WriteError::UnknownBitstreamSerialNumber)?;
 
-        if state.data_head != 0 {
-            write_page(&mut self.writer, state, &mut self.page_buffer)?;
+        if !state.segment_table.is_empty() {
+            write_page(&mut self.pages, state, &mut self.page_buffer)?;
         }
 
         Ok(())
     }
 
-    /// Returns true if the current page for the given logical bitstream contains no data.
-    pub fn page_is_empty(&mut self, bitstream_serial_number: u32) -> Result<bool, WriteError> {
+    fn write_page(&mut self, page: &Page) -> Result<(), WriteError> {
+        if self
+            .stream_states
+            .iter()
+            .any(|s| s.bitstream_serial_number == page.bitstream_serial_number())
+        {
+            return Err(WriteError::BitstreamAlreadyInitialized);
+        }
+
+        if !page.is_valid() {
+            return Err(WriteError::InvalidPage);
+        }
+
+        let page_size = page.write_raw(&mut self.page_buffer)?;
+        self.pages.extend_from_slice(&self.page_buffer[..page_size]);
+
+        Ok(())
+    }
+
+    fn page_is_empty(&self, bitstream_serial_number: u32) -> Result<bool, WriteError> {
         let state = self
             .stream_states
             .iter()
             .find(|s| s.bitstream_serial_number == bitstream_serial_number)
             .ok_or(WriteError::UnknownBitstreamSerialNumber)?;
 
-        Ok(state.data_head == 0)
+        Ok(state.segment_table.is_empty())
     }
 }
 
-fn push_packet(state: &mut StreamState, packet_data: &[u8]) {
-    let size = packet_data.len();
-    state.packet_sizes.push(size);
-    state.data_buffer[state.data_head..state.data_head + size]
-        .copy_from_slice(&packet_data[state.data_head..state.data_head + size]);
+/// Queues the packet on the current page of the logical bitstream. Packets that don't fit
+/// into the free segments are split, the pages they continue on are written right away.
+///
+/// Returns true if the packet was split.
+fn queue_packet<W: Write>(
+    writer: &mut W,
+    state: &mut StreamState,
+    page_buffer: &mut [u8],
+    packet_data: &[u8],
+    granule_position: u64,
+) -> Result<bool, WriteError> {
+    let mut data = packet_data;
+    let mut is_split = false;
+    loop {
+        let free_segments = 255 - state.segment_table.len();
+
+        if data.len() / 255 < free_segments {
+            state.granule_position = granule_position;
+            push_segments(state, data, true);
+            return Ok(is_split);
+        }
+
+        // Pages on which no packet ends get the granule position `-1` (u64::MAX).
+        let (head, tail) = data.split_at(free_segments * 255);
+        push_segments(state, head, false);
+        write_page(writer, state, page_buffer)?;
+        state.header_type = CONTINUATION_VALUE;
+        data = tail;
+        is_split = true;
+    }
+}
+
+/// Adds the data and its lacing values to the current page. The lacing value that ends the
+/// packet is only added if the packet is complete.
+fn push_segments(state: &mut StreamState, data: &[u8], is_complete: bool) {
+    let size = data.len();
+    state.data_buffer[state.data_head..state.data_head + size].copy_from_slice(data);
     state.data_head += size;
+
+    let full_segments = size / 255;
+    state
+        .segment_table
+        .extend(std::iter::repeat(255).take(full_segments));
+    if is_complete {
+        // The remainder is always smaller than 255.
+        state
+            .segment_table
+            .push(u8::try_from(size % 255).unwrap_or_default());
+    }
 }
 
 fn write_page<W: Write>(
@@ -236,25 +370,12 @@ fn write_page<W: Write>(
     state: &mut StreamState,
     page_buffer: &mut [u8],
 ) -> Result<(), WriteError> {
-    // Write out the segment table.
-    let mut segment_count: u8 = 0;
-    for packet_size in state.packet_sizes.iter() {
-        let full_segments = u8::try_from(packet_size / 255)?;
-        for _ in 0..full_segments {
-            page_buffer[SEGMENT_TABLE_INDEX + usize::from(segment_count)] = 255;
-            segment_count += 1;
-        }
-
-        let remainder = u8::try_from(packet_size % 255)?;
-        if remainder > 0 {
-            page_buffer[SEGMENT_TABLE_INDEX + usize::from(segment_count)] = remainder;
-            segment_count += 1;
-        }
-    }
+    let segment_count = state.segment_table.len();
 
     // Assemble the page.
     page_buffer[HEADER_TYPE_INDEX] = state.header_type;
-    if segment_count == 255 {
+    if state.segment_table.iter().all(|lace| *lace == 255) {
+        // No packet ends on this page.
         page_buffer[GRANULE_POSITION_RANGE].copy_from_slice(&u64::MAX.to_le_bytes());
     } else {
         page_buffer[GRANULE_POSITION_RANGE].copy_from_slice(&state.granule_position.to_le_bytes());
@@ -264,20 +385,22 @@ fn write_page<W: Write>(
     page_buffer[PAGE_SEQUENCE_NUMBER_RANGE]
         .copy_from_slice(&state.page_sequence_number.to_le_bytes());
     page_buffer[CRC32_RANGE].copy_from_slice(&[0, 0, 0, 0]);
-    page_buffer[SEGMENT_COUNT_INDEX] = segment_count;
+    page_buffer[SEGMENT_COUNT_INDEX] = u8::try_from(segment_count)?;
 
-    let data_start = SEGMENT_TABLE_INDEX + usize::from(segment_count);
+    let data_start = SEGMENT_TABLE_INDEX + segment_count;
     let data_end = data_start + state.data_head;
+    page_buffer[SEGMENT_TABLE_INDEX..data_start].copy_from_slice(&state.segment_table);
     page_buffer[data_start..data_end].copy_from_slice(&state.data_buffer[..state.data_head]);
 
-    let crc32 = crc32(&page_buffer[..data_start + state.data_head]);
+    let crc32 = crc32(&page_buffer[..data_end]);
     page_buffer[CRC32_RANGE].copy_from_slice(&crc32.to_le_bytes());
 
     // Write out the page and reset the state of the stream.
     writer.write_all(&page_buffer[..data_end])?;
 
-    state.packet_sizes.clear();
+    state.segment_table.clear();
     state.data_head = 0;
+    state.header_type = 0x0;
 
     state.page_sequence_number += 1;
 
@@ -451,6 +574,29 @@ mod tests {
         assert_page(&buffer, offset, 0, 42, 127, 1, vec![&[0xFF, 0xFF]]);
     }
 
+    #[test]
+    fn test_write_multiple_packets() {
+        let mut bw = StreamWriter::new(Cursor::new(vec![]));
+        bw.begin_logical_stream(42, &[0x0, 0x1, 0x2, 0x4]).unwrap();
+        bw.push_packet(42, &[0xAA, 0xAB, 0xAC], 100).unwrap();
+        bw.push_packet(42, &[0xBA, 0xBB, 0xBC, 0xBD], 127).unwrap();
+        bw.flush(42).unwrap();
+
+        let buffer = bw.into_inner().into_inner();
+
+        // The second packet is copied from its own start, not from the end of the first one.
+        let offset = assert_page(&buffer, 0, BOS_VALUE, 42, 0, 0, vec![&[0x0, 0x1, 0x2, 0x4]]);
+        assert_page(
+            &buffer,
+            offset,
+            0,
+            42,
+            127,
+            1,
+            vec![&[0xAA, 0xAB, 0xAC], &[0xBA, 0xBB, 0xBC, 0xBD]],
+        );
+    }
+
     #[test]
     fn test_dont_flush_empty_page() {
         let buffer: Vec<u8> = vec![];
@@ -466,7 +612,162 @@ mod tests {
         assert_eq!(buffer.len(), 32)
     }
 
-    // TODO test the flushing on packets if full
-    // TODO test the "continuation" of packets.
+    #[test]
+    #[cfg(feature = "reader")]
+    fn test_write_page() {
+        let d: Vec<u8> = vec![
+            0x4F, 0x67, 0x67, 0x53, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x4A, 0xC9, 0x09, 0xB6, 0x00, 0x00, 0x00, 0x00, 0xF9, 0x20, 0x89, 0xF8, 0x01, 0x13,
+            0x4F, 0x70, 0x75, 0x73, 0x48, 0x65, 0x61, 0x64, 0x01, 0x02, 0x38, 0x01, 0x80, 0xBB,
+            0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+
+        let mut sr = crate::StreamReader::new(Cursor::new(d.clone()));
+        let mut page = Page::default();
+        sr.next_page(&mut page).unwrap();
+
+        let mut bw = StreamWriter::new(Cursor::new(vec![]));
+        bw.write_page(&page).unwrap();
+
+        page.set_bitstream_serial_number(42);
+        page.set_page_sequence_number(7);
+        bw.write_page(&page).unwrap();
+
+        let buffer = bw.into_inner().into_inner();
+
+        // The first page is written verbatim.
+        assert_eq!(&buffer[..d.len()], d.as_slice());
+
+        let mut rewritten = buffer[d.len()..].to_vec();
+        assert_page(&rewritten, 0, BOS_VALUE, 42, 0, 7, vec![&d[28..]]);
+
+        let target_crc = parse_u32_le(&rewritten[CRC32_RANGE]);
+        rewritten[CRC32_RANGE].copy_from_slice(&[0, 0, 0, 0]);
+        assert_eq!(target_crc, crc32(&rewritten));
+    }
+
+    #[test]
+    fn test_write_constructed_page() {
+        let big_packet = [0xAA; 300];
+        let mut page = Page::from_packets(&[&[0x1, 0x2, 0x3], &big_packet]).unwrap();
+        assert_eq!(page.segment_table(), &[3, 255, 45]);
+        assert_eq!(
+            Page::from_packets(&[&[0xAA; 255]]).unwrap().segment_table(),
+            &[255, 0]
+        );
+        page.set_header_type(BOS_VALUE);
+        page.set_bitstream_serial_number(42);
+        page.set_granule_position(10);
+
+        let mut bw = StreamWriter::new(Cursor::new(vec![]));
+        bw.write_page(&page).unwrap();
+        let buffer = bw.into_inner().into_inner();
+        assert_page(
+            &buffer,
+            0,
+            BOS_VALUE,
+            42,
+            10,
+            0,
+            vec![&[0x1, 0x2, 0x3], &big_packet],
+        );
+
+        assert!(Page::from_packets(&[&[0xAA; 255 * 255]]).is_none());
+
+        // The segment table doesn't match the payload.
+        let page = Page::new(vec![2], vec![0x1]);
+        let mut bw = StreamWriter::new(Cursor::new(vec![]));
+        assert!(matches!(bw.write_page(&page), Err(WriteError::InvalidPage)));
+    }
+
+    #[test]
+    fn test_write_page_of_open_stream() {
+        let mut bw = StreamWriter::new(Cursor::new(vec![]));
+        bw.begin_logical_stream(42, &[0x0, 0x1, 0x2, 0x4]).unwrap();
+
+        let mut page = Page::default();
+        page.set_bitstream_serial_number(42);
+
+        assert!(matches!(
+            bw.write_page(&page),
+            Err(WriteError::BitstreamAlreadyInitialized)
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "reader")]
+    fn test_lacing() {
+        let mut bw = StreamWriter::new(Cursor::new(vec![]));
+        bw.begin_logical_stream(42, &[0x0, 0x1, 0x2, 0x4]).unwrap();
+        // Packets whose size is a multiple of 255 need a terminating lace.
+        bw.push_packet(42, &[], 1).unwrap();
+        bw.push_packet(42, &[0xAA; 510], 2).unwrap();
+        // The page is flushed once its segment table is full.
+        for i in 0..300 {
+            bw.push_packet(42, &[0xBB], 3 + i).unwrap();
+        }
+        bw.end_logical_stream(42, &[0xCC], 303).unwrap();
+        let buffer = bw.into_inner().into_inner();
+
+        // The second page holds 3 + 252 segments.
+        assert_eq!(buffer[32 + SEGMENT_COUNT_INDEX], 255);
+        assert_eq!(
+            &buffer[32 + SEGMENT_TABLE_INDEX..32 + SEGMENT_TABLE_INDEX + 5],
+            &[0, 255, 255, 0, 1]
+        );
+
+        let mut sr = crate::StreamReader::new(Cursor::new(buffer));
+        let mut packet = crate::Packet::default();
+        let mut sizes = Vec::new();
+        while sr.next_packet(&mut packet).unwrap() == crate::ReadStatus::Ok {
+            sizes.push(packet.data().len());
+        }
+        assert_eq!(sizes.len(), 304);
+        assert_eq!(&sizes[..4], &[4, 0, 510, 1]);
+    }
+
+    #[test]
+    #[cfg(feature = "reader")]
+    fn test_lacing_split_packet() {
+        let mut bw = StreamWriter::new(Cursor::new(vec![]));
+        // The biggest BOS packet still has its terminating lace on the page.
+        bw.begin_logical_stream(42, &[0xAA; 65_024]).unwrap();
+        assert!(matches!(
+            bw.begin_logical_stream(43, &[0xAA; 65_025]),
+            Err(WriteError::InitialPacketTooBig)
+        ));
+        // The terminating lace of the packet continues on the next page.
+        bw.push_packet(42, &[0xBB; 65_025], 1).unwrap();
+        bw.end_logical_stream(42, &[], 2).unwrap();
+        let buffer = bw.into_inner().into_inner();
+
+        let mut sr = crate::StreamReader::new(Cursor::new(buffer.clone()));
+        let mut page = crate::Page::default();
+        let mut pages = Vec::new();
+        while sr.next_page(&mut page).unwrap() == crate::ReadStatus::Ok {
+            pages.push(page.clone());
+        }
+        assert_eq!(pages.len(), 4);
+        assert_eq!(pages[0].segment_table().len(), 255);
+        assert_eq!(pages[0].segment_table().last(), Some(&254));
+        assert!(pages[1].segment_table().iter().all(|lace| *lace == 255));
+        assert_eq!(pages[1].segment_table().len(), 255);
+        assert_eq!(pages[1].granule_position(), u64::MAX);
+        // A split packet ends on its own page.
+        assert!(pages[2].is_continuation());
+        assert_eq!(pages[2].segment_table(), &[0]);
+        assert_eq!(pages[2].granule_position(), 1);
+        assert!(pages[3].is_eos());
+        assert_eq!(pages[3].segment_table(), &[0]);
+
+        let mut sr = crate::StreamReader::new(Cursor::new(buffer));
+        let mut packet = crate::Packet::default();
+        let mut sizes = Vec::new();
+        while sr.next_packet(&mut packet).unwrap() == crate::ReadStatus::Ok {
+            sizes.push(packet.data().len());
+        }
+        assert_eq!(sizes, vec![65_024, 65_025, 0]);
+    }
+
     // TODO test if EOS flushes the last page.
 }
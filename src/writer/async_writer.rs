@@ -0,0 +1,269 @@
+//! Async bitstream writer.
+
+use std::future::poll_fn;
+use std::io::ErrorKind;
+use std::pin::Pin;
+
+use futures_io::AsyncWrite;
+
+use super::BitStreamWriter;
+use crate::{Page, WriteError};
+
+/// Generic OGG stream writer for async writers.
+///
+/// Tokio writers can be used by wrapping them into a `TokioCompat`
+/// (requires the "tokio" feature).
+#[derive(Clone, Debug)]
+pub struct AsyncStreamWriter<W: AsyncWrite + Unpin> {
+    inner: BitStreamWriter,
+    writer: W,
+    written: usize,
+}
+
+impl<W: AsyncWrite + Unpin> AsyncStreamWriter<W> {
+    /// Creates a new `AsyncStreamWriter`.
+    pub fn new(writer: W) -> Self {
+        Self {
+            inner: Default::default(),
+            writer,
+            written: 0,
+        }
+    }
+
+    /// Consumes the `AsyncStreamWriter` and returns the writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Starts a new logical stream. Behaves like `StreamWriter::begin_logical_stream()`.
+    pub async fn begin_logical_stream(
+        &mut self,
+        bitstream_serial_number: u32,
+        first_packet_data: &[u8],
+    ) -> Result<(), WriteError> {
+        self.inner
+            .begin_logical_stream(bitstream_serial_number, first_packet_data)?;
+        self.write_pages().await
+    }
+
+    /// Ends the logical stream. Behaves like `StreamWriter::end_logical_stream()`.
+    ///
+    /// The writer is flushed after the last page was written.
+    pub async fn end_logical_stream(
+        &mut self,
+        bitstream_serial_number: u32,
+        last_packet_data: &[u8],
+        granule_position: u64,
+    ) -> Result<(), WriteError> {
+        self.inner.end_logical_stream(
+            bitstream_serial_number,
+            last_packet_data,
+            granule_position,
+        )?;
+        self.write_pages().await?;
+        self.flush_writer().await
+    }
+
+    /// Queues the the given data as a packet. Behaves like `StreamWriter::push_packet()`.
+    ///
+    /// The returned future only completes once the pages that were finished by the packet
+    /// are accepted by the writer.
+    pub async fn push_packet(
+        &mut self,
+        bitstream_serial_number: u32,
+        packet_data: &[u8],
+        granule_position: u64,
+    ) -> Result<(), WriteError> {
+        self.inner
+            .push_packet(bitstream_serial_number, packet_data, granule_position)?;
+        self.write_pages().await
+    }
+
+    /// The current page of the logical bitstream is written and a new page is started.
+    ///
+    /// The writer is flushed as well, so that the written pages become visible.
+    pub async fn flush(&mut self, bitstream_serial_number: u32) -> Result<(), WriteError> {
+        self.inner.flush(bitstream_serial_number)?;
+        self.write_pages().await?;
+        self.flush_writer().await
+    }
+
+    /// Writes the given page verbatim to the writer. Behaves like `StreamWriter::write_page()`.
+    pub async fn write_page(&mut self, page: &Page) -> Result<(), WriteError> {
+        self.inner.write_page(page)?;
+        self.write_pages().await
+    }
+
+    /// Returns true if the current page for the given logical bitstream contains no data.
+    pub fn page_is_empty(&mut self, bitstream_serial_number: u32) -> Result<bool, WriteError> {
+        self.inner.page_is_empty(bitstream_serial_number)
+    }
+
+    /// Writes the assembled pages to the writer.
+    ///
+    /// Keeps track of the data already written, so that a cancelled write is resumed
+    /// with the next call.
+    async fn write_pages(&mut self) -> Result<(), WriteError> {
+        let Self {
+            inner,
+            writer,
+            written,
+        } = self;
+
+        while *written < inner.pages.len() {
+            let pending = &inner.pages[*written..];
+            match poll_fn(|cx| Pin::new(&mut *writer).poll_write(cx, pending)).await {
+                Ok(0) => {
+                    return Err(WriteError::IoError(std::io::Error::new(
+                        ErrorKind::WriteZero,
+                        "failed to write the whole page",
+                    )));
+                }
+                Ok(size) => *written += size,
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        inner.pages.clear();
+        *written = 0;
+
+        Ok(())
+    }
+
+    /// Flushes the writer, so that all data written to it reaches its destination.
+    async fn flush_writer(&mut self) -> Result<(), WriteError> {
+        let writer = &mut self.writer;
+        poll_fn(|cx| Pin::new(&mut *writer).poll_flush(cx)).await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::panic)]
+    #![allow(clippy::unwrap_used)]
+
+    use std::io::Cursor;
+    use std::task::{Context, Poll};
+
+    use super::*;
+    use crate::test_util::block_on;
+    use crate::StreamWriter;
+
+    /// Writer that accepts the data in small chunks and is pending before every chunk.
+    #[derive(Default)]
+    struct PendingWriter {
+        data: Vec<u8>,
+        is_pending: bool,
+    }
+
+    impl AsyncWrite for PendingWriter {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<std::io::Result<usize>> {
+            self.is_pending = !self.is_pending;
+            if self.is_pending {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+
+            let size = usize::min(buf.len(), 1000);
+            self.data.extend_from_slice(&buf[..size]);
+
+            Poll::Ready(Ok(size))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    /// Writer that only makes the data visible once it's flushed.
+    #[derive(Default)]
+    struct BufferedWriter {
+        buffer: Vec<u8>,
+        visible: Vec<u8>,
+    }
+
+    impl AsyncWrite for BufferedWriter {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<std::io::Result<usize>> {
+            self.buffer.extend_from_slice(buf);
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(
+            mut self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+        ) -> Poll<std::io::Result<()>> {
+            let Self { buffer, visible } = &mut *self;
+            visible.append(buffer);
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            self.poll_flush(cx)
+        }
+    }
+
+    #[test]
+    fn test_flush_writer() {
+        let mut aw = AsyncStreamWriter::new(BufferedWriter::default());
+        block_on(aw.begin_logical_stream(1, &[0x1])).unwrap();
+        block_on(aw.push_packet(1, &[0x2, 0x3], 10)).unwrap();
+        block_on(aw.flush(1)).unwrap();
+
+        let mut sw = StreamWriter::new(Cursor::new(vec![]));
+        sw.begin_logical_stream(1, &[0x1]).unwrap();
+        sw.push_packet(1, &[0x2, 0x3], 10).unwrap();
+        sw.flush(1).unwrap();
+        let flushed = sw.into_inner().into_inner();
+        assert_eq!(aw.writer.visible, flushed);
+
+        block_on(aw.push_packet(1, &[0x4], 20)).unwrap();
+        assert_eq!(aw.writer.visible, flushed);
+        block_on(aw.end_logical_stream(1, &[0x5], 30)).unwrap();
+
+        let mut sw = StreamWriter::new(Cursor::new(vec![]));
+        sw.begin_logical_stream(1, &[0x1]).unwrap();
+        sw.push_packet(1, &[0x2, 0x3], 10).unwrap();
+        sw.flush(1).unwrap();
+        sw.push_packet(1, &[0x4], 20).unwrap();
+        sw.end_logical_stream(1, &[0x5], 30).unwrap();
+        let writer = aw.into_inner();
+        assert!(writer.buffer.is_empty());
+        assert_eq!(writer.visible, sw.into_inner().into_inner());
+    }
+
+    #[test]
+    fn test_same_output_as_stream_writer() {
+        let big_packet = vec![0xAA; 100_000];
+
+        let mut sw = StreamWriter::new(Cursor::new(vec![]));
+        sw.begin_logical_stream(1, &[0x1]).unwrap();
+        sw.push_packet(1, &[0x2, 0x3], 10).unwrap();
+        sw.push_packet(1, &big_packet, 20).unwrap();
+        sw.flush(1).unwrap();
+        sw.end_logical_stream(1, &[0x4], 30).unwrap();
+
+        let mut aw = AsyncStreamWriter::new(PendingWriter::default());
+        block_on(aw.begin_logical_stream(1, &[0x1])).unwrap();
+        block_on(aw.push_packet(1, &[0x2, 0x3], 10)).unwrap();
+        block_on(aw.push_packet(1, &big_packet, 20)).unwrap();
+        block_on(aw.flush(1)).unwrap();
+        block_on(aw.end_logical_stream(1, &[0x4], 30)).unwrap();
+
+        assert_eq!(aw.into_inner().data, sw.into_inner().into_inner());
+    }
+}
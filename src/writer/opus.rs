@@ -0,0 +1,175 @@
+//! Ogg Opus logical bitstream writer.
+
+use std::io::Write;
+
+use super::StreamWriter;
+use crate::opus::packet_duration;
+use crate::{OpusHead, OpusTags, WriteError};
+
+/// Writes an Ogg Opus logical bitstream.
+///
+/// The identification header is written alone on the BOS page and the comment header ends its
+/// page, so that the first audio packet starts a new page. The granule positions are counted
+/// in samples at 48 kHz from the durations of the audio packets and include the pre-skip.
+#[derive(Clone, Debug)]
+pub struct OpusWriter<W: Write> {
+    inner: StreamWriter<W>,
+    bitstream_serial_number: u32,
+    granule_position: u64,
+}
+
+impl<W: Write> OpusWriter<W> {
+    /// Creates a new `OpusWriter` and writes the header pages of the logical bitstream.
+    pub fn new(
+        writer: W,
+        bitstream_serial_number: u32,
+        head: &OpusHead,
+        tags: &OpusTags,
+    ) -> Result<Self, WriteError> {
+        let tags = tags.to_packet().ok_or(WriteError::InvalidPacket)?;
+
+        let mut inner = StreamWriter::new(writer);
+        inner.begin_logical_stream(bitstream_serial_number, &head.to_packet())?;
+        inner.push_packet(bitstream_serial_number, &tags, 0)?;
+        inner.flush(bitstream_serial_number)?;
+
+        Ok(Self {
+            inner,
+            bitstream_serial_number,
+            granule_position: 0,
+        })
+    }
+
+    /// Returns the granule position after the audio packets written so far.
+    pub fn granule_position(&self) -> u64 {
+        self.granule_position
+    }
+
+    /// Queues an audio packet. Its duration is derived from the TOC byte of the packet.
+    pub fn push_packet(&mut self, packet_data: &[u8]) -> Result<(), WriteError> {
+        self.granule_position = self.next_granule_position(packet_data)?;
+        self.inner.push_packet(
+            self.bitstream_serial_number,
+            packet_data,
+            self.granule_position,
+        )
+    }
+
+    /// The current page is written and a new page is started.
+    pub fn flush(&mut self) -> Result<(), WriteError> {
+        self.inner.flush(self.bitstream_serial_number)
+    }
+
+    /// Ends the logical bitstream with the last audio packet and returns the writer.
+    ///
+    /// `end_trim` is the number of samples at the end of the last packet that are discarded
+    /// by the decoder. It must not exceed the duration of the last packet.
+    pub fn finish(mut self, last_packet_data: &[u8], end_trim: u32) -> Result<W, WriteError> {
+        let duration = packet_duration(last_packet_data).ok_or(WriteError::InvalidPacket)?;
+        if end_trim > duration {
+            return Err(WriteError::InvalidEndTrim);
+        }
+
+        let granule_position = self.next_granule_position(last_packet_data)? - u64::from(end_trim);
+        self.inner.end_logical_stream(
+            self.bitstream_serial_number,
+            last_packet_data,
+            granule_position,
+        )?;
+        Ok(self.inner.into_inner())
+    }
+
+    /// Returns the granule position after the given audio packet.
+    fn next_granule_position(&self, packet_data: &[u8]) -> Result<u64, WriteError> {
+        let duration = packet_duration(packet_data).ok_or(WriteError::InvalidPacket)?;
+        Ok(self.granule_position + u64::from(duration))
+    }
+}
+
+#[cfg(all(test, feature = "reader"))]
+mod tests {
+    #![allow(clippy::panic)]
+    #![allow(clippy::unwrap_used)]
+
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::{Packet, ReadStatus, StreamReader};
+
+    /// Returns a CELT packet of 20 ms with the given number of frames.
+    fn audio_packet(frame_count: u8) -> Vec<u8> {
+        match frame_count {
+            1 => vec![0xF8, 0xAA, 0xBB],
+            _ => vec![0xFB, frame_count, 0xAA, 0xBB],
+        }
+    }
+
+    fn read_packets(data: Vec<u8>) -> Vec<Packet> {
+        let mut reader = StreamReader::new(Cursor::new(data));
+        let mut packets = Vec::new();
+        loop {
+            let mut packet = Packet::default();
+            match reader.next_packet(&mut packet).unwrap() {
+                ReadStatus::Ok => packets.push(packet),
+                ReadStatus::Eof => return packets,
+                status => panic!("unexpected read status: {:?}", status),
+            }
+        }
+    }
+
+    fn tags() -> OpusTags {
+        let mut tags = OpusTags::new("test");
+        tags.add_comment("TITLE", "Foo");
+        tags
+    }
+
+    #[test]
+    fn test_opus_writer() {
+        let head = OpusHead::new(2, 312, 44_100).unwrap();
+
+        let mut writer = OpusWriter::new(Vec::new(), 7, &head, &tags()).unwrap();
+        writer.push_packet(&audio_packet(1)).unwrap();
+        writer.push_packet(&audio_packet(3)).unwrap();
+        assert_eq!(writer.granule_position(), 3840);
+        writer.flush().unwrap();
+        writer.push_packet(&audio_packet(1)).unwrap();
+        let data = writer.finish(&audio_packet(2), 500).unwrap();
+
+        let packets = read_packets(data);
+        assert_eq!(packets.len(), 6);
+
+        let read_head = OpusHead::parse(packets[0].data()).unwrap();
+        assert_eq!(read_head, head);
+        assert_eq!(read_head.input_sample_rate(), 44_100);
+        assert_eq!(
+            OpusTags::parse(packets[1].data()).unwrap().comment("title"),
+            Some("Foo")
+        );
+
+        // The headers end their pages and the last packet is trimmed on the EOS page.
+        let granule_positions: Vec<u64> = packets.iter().map(|p| p.granule_position()).collect();
+        assert_eq!(granule_positions, [0, 0, 3840, 3840, 4800, 6220]);
+        assert!(packets[5].is_eos());
+        assert_eq!(packets[5].data(), audio_packet(2).as_slice());
+    }
+
+    #[test]
+    fn test_invalid_packets() {
+        let head = OpusHead::new(1, 0, 48_000).unwrap();
+
+        let mut writer = OpusWriter::new(Vec::new(), 7, &head, &tags()).unwrap();
+        assert!(matches!(
+            writer.push_packet(&[]),
+            Err(WriteError::InvalidPacket)
+        ));
+        // 49 frames of 2.5 ms are longer than 120 ms.
+        assert!(matches!(
+            writer.push_packet(&[0x83, 49]),
+            Err(WriteError::InvalidPacket)
+        ));
+        assert!(matches!(
+            writer.finish(&audio_packet(1), 961),
+            Err(WriteError::InvalidEndTrim)
+        ));
+    }
+}
@@ -0,0 +1,578 @@
+//! Skeleton logical bitstream writer.
+
+use std::convert::TryFrom;
+use std::io::{Seek, SeekFrom, Write};
+
+use super::{BitStreamWriter, StreamState};
+use crate::{Fisbone, Fishead, Keypoint, SkeletonIndex, WriteError};
+
+/// Writes a chain whose logical bitstreams are described by a Skeleton logical bitstream.
+///
+/// The headers are written in the order of the Skeleton specification: the fishead BOS page
+/// comes first, followed by the BOS pages of the other logical bitstreams. The fisbone and
+/// index packets are followed by the secondary header packets of the other logical bitstreams.
+/// The Skeleton EOS page ends the headers in front of the first data page.
+#[derive(Clone, Debug)]
+pub struct SkeletonWriter<W: Write> {
+    inner: BitStreamWriter,
+    writer: W,
+    bytes_written: u64,
+    content_byte_offset: u64,
+    bitstream_serial_number: u32,
+    fishead: Fishead,
+    fisbones: Vec<Fisbone>,
+    indexes: Vec<IndexState>,
+    header_state: HeaderState,
+}
+
+/// The headers that were written so far.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+enum HeaderState {
+    Empty,
+    BosPages,
+    SecondaryHeaders,
+    Finished,
+}
+
+/// The keypoints of an indexed logical bitstream.
+#[derive(Clone, Debug)]
+struct IndexState {
+    bitstream_serial_number: u32,
+    keypoint_capacity: usize,
+    keypoints: Vec<Keypoint>,
+    /// The offset and page sequence number of the reserved index packet.
+    reserved: Option<(u64, u32)>,
+    first_granule_position: Option<u64>,
+    last_granule_position: Option<u64>,
+}
+
+impl<W: Write> SkeletonWriter<W> {
+    /// Creates a new `SkeletonWriter` with the serial number and fishead of the Skeleton
+    /// logical bitstream.
+    pub fn new(writer: W, bitstream_serial_number: u32, fishead: Fishead) -> Self {
+        Self {
+            inner: Default::default(),
+            writer,
+            bytes_written: 0,
+            content_byte_offset: 0,
+            bitstream_serial_number,
+            fishead,
+            fisbones: Vec::new(),
+            indexes: Vec::new(),
+            header_state: HeaderState::Empty,
+        }
+    }
+
+    /// Consumes the `SkeletonWriter` and returns the writer. The fishead and the reserved
+    /// index packets are not back-patched, use `finish()` instead.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Starts a new logical bitstream, which is described by the given fisbone. Caller needs
+    /// to provide the first packet, which will be written to the writer right away.
+    ///
+    /// All logical bitstreams need to be started before the first secondary header packet.
+    pub fn begin_logical_stream(
+        &mut self,
+        fisbone: Fisbone,
+        first_packet_data: &[u8],
+    ) -> Result<(), WriteError> {
+        if self.header_state > HeaderState::BosPages {
+            return Err(WriteError::HeadersFinished);
+        }
+
+        self.write_fishead()?;
+        self.inner
+            .begin_logical_stream(fisbone.bitstream_serial_number(), first_packet_data)?;
+        self.fisbones.push(fisbone);
+        self.write_pages()
+    }
+
+    /// Queues a secondary header packet of the logical bitstream, like the comment header
+    /// of Vorbis. The fisbone and index packets are written in front of the first one.
+    pub fn push_header_packet(
+        &mut self,
+        bitstream_serial_number: u32,
+        packet_data: &[u8],
+    ) -> Result<(), WriteError> {
+        if self.header_state == HeaderState::Finished {
+            return Err(WriteError::HeadersFinished);
+        }
+
+        self.write_fisbones()?;
+        self.inner
+            .push_packet(bitstream_serial_number, packet_data, 0)?;
+        self.write_pages()
+    }
+
+    /// Flushes the pages of the secondary header packets and writes the Skeleton EOS page.
+    ///
+    /// Is called by the first data packet if needed.
+    pub fn finish_headers(&mut self) -> Result<(), WriteError> {
+        if self.header_state == HeaderState::Finished {
+            return Ok(());
+        }
+
+        self.write_fisbones()?;
+        for fisbone in self.fisbones.iter() {
+            self.inner.flush(fisbone.bitstream_serial_number())?;
+        }
+        self.inner
+            .end_logical_stream(self.bitstream_serial_number, &[], 0)?;
+        self.header_state = HeaderState::Finished;
+        self.write_pages()?;
+        self.content_byte_offset = self.bytes_written;
+
+        Ok(())
+    }
+
+    /// Queues a data packet of the logical bitstream. Behaves like
+    /// `StreamWriter::push_packet()`.
+    pub fn push_packet(
+        &mut self,
+        bitstream_serial_number: u32,
+        packet_data: &[u8],
+        granule_position: u64,
+    ) -> Result<(), WriteError> {
+        self.finish_headers()?;
+        self.track_granule_position(bitstream_serial_number, granule_position);
+        self.inner
+            .push_packet(bitstream_serial_number, packet_data, granule_position)?;
+        self.write_pages()
+    }
+
+    /// Queues a data packet of the logical bitstream that contains a keyframe.
+    ///
+    /// The keyframe starts a new page. If the logical bitstream is indexed, the page is added
+    /// as a keypoint. Keypoints beyond the capacity of the index are ignored.
+    pub fn push_keyframe(
+        &mut self,
+        bitstream_serial_number: u32,
+        packet_data: &[u8],
+        granule_position: u64,
+    ) -> Result<(), WriteError> {
+        self.finish_headers()?;
+        self.flush(bitstream_serial_number)?;
+
+        let timestamp = self
+            .fisbones
+            .iter()
+            .find(|f| f.bitstream_serial_number() == bitstream_serial_number)
+            .and_then(|fisbone| fisbone.granule_time(granule_position))
+            .map(|(timestamp, _)| timestamp);
+        let byte_offset = self.bytes_written;
+
+        if let (Some(index), Some(timestamp)) = (
+            self.indexes
+                .iter_mut()
+                .find(|i| i.bitstream_serial_number == bitstream_serial_number),
+            timestamp,
+        ) {
+            let is_ordered = index.keypoints.last().map_or(true, |last| {
+                last.byte_offset() < byte_offset && last.timestamp() <= timestamp
+            });
+
+            if is_ordered && index.keypoints.len() < index.keypoint_capacity {
+                index.keypoints.push(Keypoint::new(byte_offset, timestamp));
+            }
+        }
+
+        self.push_packet(bitstream_serial_number, packet_data, granule_position)
+    }
+
+    /// The current page of the logical bitstream is written and a new page is started.
+    pub fn flush(&mut self, bitstream_serial_number: u32) -> Result<(), WriteError> {
+        self.inner.flush(bitstream_serial_number)?;
+        self.write_pages()
+    }
+
+    /// Ends the logical bitstream. Behaves like `StreamWriter::end_logical_stream()`.
+    pub fn end_logical_stream(
+        &mut self,
+        bitstream_serial_number: u32,
+        last_packet_data: &[u8],
+        granule_position: u64,
+    ) -> Result<(), WriteError> {
+        self.finish_headers()?;
+        self.track_granule_position(bitstream_serial_number, granule_position);
+        self.inner.end_logical_stream(
+            bitstream_serial_number,
+            last_packet_data,
+            granule_position,
+        )?;
+        self.write_pages()
+    }
+
+    /// Writes the fishead BOS page in front of all other pages.
+    fn write_fishead(&mut self) -> Result<(), WriteError> {
+        if self.header_state == HeaderState::Empty {
+            self.inner
+                .begin_logical_stream(self.bitstream_serial_number, &self.fishead.to_packet())?;
+            self.header_state = HeaderState::BosPages;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the fisbone packets after the BOS pages and reserves the index packets,
+    /// which are written alone on their pages.
+    fn write_fisbones(&mut self) -> Result<(), WriteError> {
+        self.write_fishead()?;
+        if self.header_state != HeaderState::BosPages {
+            return Ok(());
+        }
+
+        let serial = self.bitstream_serial_number;
+        for fisbone in self.fisbones.iter() {
+            self.inner.push_packet(serial, &fisbone.to_packet(), 0)?;
+        }
+        self.inner.flush(serial)?;
+        self.write_pages()?;
+
+        for i in 0..self.indexes.len() {
+            let page_sequence_number = self.page_sequence_number()?;
+            self.indexes[i].reserved = Some((self.bytes_written, page_sequence_number));
+
+            // The reserved packet is a valid index without keypoints.
+            let mut packet =
+                SkeletonIndex::new(self.indexes[i].bitstream_serial_number, 1, 0, 0, Vec::new())
+                    .to_packet()
+                    .unwrap_or_default();
+            packet.resize(
+                SkeletonIndex::packet_size(self.indexes[i].keypoint_capacity),
+                0,
+            );
+            self.inner.push_packet(serial, &packet, 0)?;
+            self.inner.flush(serial)?;
+            self.write_pages()?;
+        }
+
+        self.header_state = HeaderState::SecondaryHeaders;
+        Ok(())
+    }
+
+    /// Returns the sequence number of the next page of the Skeleton logical bitstream.
+    fn page_sequence_number(&self) -> Result<u32, WriteError> {
+        self.inner
+            .stream_states
+            .iter()
+            .find(|s| s.bitstream_serial_number == self.bitstream_serial_number)
+            .map(|s| s.page_sequence_number)
+            .ok_or(WriteError::UnknownBitstreamSerialNumber)
+    }
+
+    /// Remembers the first and last granule position of an indexed logical bitstream.
+    fn track_granule_position(&mut self, bitstream_serial_number: u32, granule_position: u64) {
+        if let Some(index) = self
+            .indexes
+            .iter_mut()
+            .find(|i| i.bitstream_serial_number == bitstream_serial_number)
+        {
+            index.first_granule_position.get_or_insert(granule_position);
+            index.last_granule_position = Some(granule_position);
+        }
+    }
+
+    /// Writes the assembled pages to the writer.
+    fn write_pages(&mut self) -> Result<(), WriteError> {
+        let result = self.writer.write_all(&self.inner.pages);
+        let size = self.inner.pages.len();
+        self.inner.pages.clear();
+        result?;
+
+        // Only the pages that were written count to the length of the chain.
+        self.bytes_written += u64::try_from(size)?;
+
+        Ok(())
+    }
+}
+
+impl<W: Write + Seek> SkeletonWriter<W> {
+    /// Adds a keyframe index for the logical bitstream with the given capacity of keypoints.
+    ///
+    /// Needs to be called before the first secondary header packet. The space of the index
+    /// packet is reserved in the headers and back-patched by `finish()`.
+    pub fn enable_index(
+        &mut self,
+        bitstream_serial_number: u32,
+        keypoint_capacity: usize,
+    ) -> Result<(), WriteError> {
+        if self.header_state > HeaderState::BosPages {
+            return Err(WriteError::HeadersFinished);
+        }
+
+        if self
+            .indexes
+            .iter()
+            .any(|i| i.bitstream_serial_number == bitstream_serial_number)
+        {
+            return Err(WriteError::BitstreamAlreadyInitialized);
+        }
+
+        self.indexes.push(IndexState {
+            bitstream_serial_number,
+            keypoint_capacity,
+            keypoints: Vec::with_capacity(keypoint_capacity),
+            reserved: None,
+            first_granule_position: None,
+            last_granule_position: None,
+        });
+
+        Ok(())
+    }
+
+    /// Back-patches the fishead and the reserved index packets and returns the writer.
+    ///
+    /// The fishead gets the length of the chain and the offset of its first data page. The
+    /// pending pages of logical bitstreams that were not ended are flushed first, they stay
+    /// without an EOS page. The writer is positioned at the end of the written pages
+    /// afterwards.
+    pub fn finish(mut self) -> Result<W, WriteError> {
+        self.finish_headers()?;
+
+        let open_streams: Vec<u32> = self
+            .inner
+            .stream_states
+            .iter()
+            .map(|s| s.bitstream_serial_number)
+            .collect();
+        for bitstream_serial_number in open_streams {
+            self.inner.flush(bitstream_serial_number)?;
+        }
+        self.write_pages()?;
+
+        let end = self.writer.stream_position()?;
+        let chain_start = end.saturating_sub(self.bytes_written);
+
+        self.fishead.set_segment_length(self.bytes_written);
+        self.fishead
+            .set_content_byte_offset(self.content_byte_offset);
+        let mut pages = BitStreamWriter::default();
+        pages.begin_logical_stream(self.bitstream_serial_number, &self.fishead.to_packet())?;
+        self.writer.seek(SeekFrom::Start(chain_start))?;
+        self.writer.write_all(&pages.pages)?;
+
+        for index in self.indexes.iter() {
+            let (byte_offset, page_sequence_number) = match index.reserved {
+                Some(reserved) => reserved,
+                None => continue,
+            };
+
+            let fisbone = self
+                .fisbones
+                .iter()
+                .find(|f| f.bitstream_serial_number() == index.bitstream_serial_number);
+            let sample_time = |granule_position: Option<u64>| {
+                granule_position
+                    .and_then(|granule_position| fisbone?.granule_time(granule_position))
+                    .map_or(0, |(timestamp, _)| timestamp)
+            };
+            let timestamp_denominator = fisbone
+                .map(|fisbone| fisbone.granule_rate().0)
+                .filter(|numerator| *numerator > 0)
+                .unwrap_or(1);
+
+            // The keypoints are ordered when they are added.
+            let mut packet = SkeletonIndex::new(
+                index.bitstream_serial_number,
+                timestamp_denominator,
+                sample_time(index.first_granule_position),
+                sample_time(index.last_granule_position),
+                index.keypoints.clone(),
+            )
+            .to_packet()
+            .unwrap_or_default();
+            packet.resize(SkeletonIndex::packet_size(index.keypoint_capacity), 0);
+
+            // The index packet is assembled on the same pages as the reserved one.
+            let mut pages = BitStreamWriter::default();
+            pages.stream_states.push(StreamState {
+                bitstream_serial_number: self.bitstream_serial_number,
+                page_sequence_number,
+                ..Default::default()
+            });
+            pages.push_packet(self.bitstream_serial_number, &packet, 0)?;
+            pages.flush(self.bitstream_serial_number)?;
+
+            self.writer
+                .seek(SeekFrom::Start(chain_start + byte_offset))?;
+            self.writer.write_all(&pages.pages)?;
+        }
+
+        self.writer.seek(SeekFrom::Start(end))?;
+
+        Ok(self.writer)
+    }
+}
+
+#[cfg(all(test, feature = "reader"))]
+mod tests {
+    #![allow(clippy::panic)]
+    #![allow(clippy::unwrap_used)]
+
+    use std::io::Cursor;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{FileReader, Packet, Page, ReadStatus};
+
+    /// Writes a chain with a video bitstream, which has keyframes every 10 frames at 25 fps.
+    fn write_video(keypoint_capacity: usize) -> SkeletonWriter<Cursor<Vec<u8>>> {
+        let mut fisbone = Fisbone::new(1, "video/x-test", (25, 1), 0);
+        fisbone.set_header_packets(2);
+
+        let mut sw = SkeletonWriter::new(Cursor::new(vec![]), 10, Fishead::new((0, 1), (0, 1)));
+        sw.enable_index(1, keypoint_capacity).unwrap();
+        sw.begin_logical_stream(fisbone, b"video").unwrap();
+        sw.push_header_packet(1, b"comment").unwrap();
+
+        for frame in 1..=100_u64 {
+            let mut data = frame.to_le_bytes().to_vec();
+            data.resize(1000, 0xAA);
+            if frame % 10 == 1 {
+                sw.push_keyframe(1, &data, frame).unwrap();
+            } else {
+                sw.push_packet(1, &data, frame).unwrap();
+            }
+        }
+        sw.end_logical_stream(1, &[0xBB], 101).unwrap();
+
+        sw
+    }
+
+    #[test]
+    fn test_header_order() {
+        let data = write_video(10).finish().unwrap().into_inner();
+        let mut fr = FileReader::new(Cursor::new(data));
+
+        let mut packets = Vec::new();
+        let mut packet = Packet::default();
+        while packets.len() < 7 {
+            assert_eq!(fr.next_packet(&mut packet).unwrap(), ReadStatus::Ok);
+            packets.push(packet.clone());
+        }
+
+        assert_eq!(packets[0].bitstream_serial_number(), 10);
+        assert!(packets[0].is_bos());
+        assert_eq!(Fishead::parse(packets[0].data()).unwrap().version(), (4, 0));
+
+        assert_eq!(packets[1].bitstream_serial_number(), 1);
+        assert!(packets[1].is_bos());
+
+        let fisbone = Fisbone::parse(packets[2].data()).unwrap();
+        assert_eq!(fisbone.bitstream_serial_number(), 1);
+        assert_eq!(fisbone.content_type(), Some("video/x-test"));
+        assert_eq!(fisbone.granule_rate(), (25, 1));
+        assert_eq!(fisbone.header_packets(), 2);
+
+        assert!(SkeletonIndex::parse(packets[3].data()).is_some());
+        assert_eq!(packets[4].data(), b"comment");
+
+        assert_eq!(packets[5].bitstream_serial_number(), 10);
+        assert!(packets[5].is_eos());
+        assert!(packets[5].data().is_empty());
+
+        assert_eq!(packets[6].data()[0], 1);
+    }
+
+    #[test]
+    fn test_index() {
+        let data = write_video(5).finish().unwrap().into_inner();
+        let mut fr = FileReader::new(Cursor::new(data.clone()));
+
+        // The fishead contains the length of the chain and the offset of the first data page.
+        let mut page = Page::default();
+        assert_eq!(fr.next_page(&mut page).unwrap(), ReadStatus::Ok);
+        let fishead = Fishead::parse(page.data()).unwrap();
+        assert_eq!(fishead.segment_length(), u64::try_from(data.len()).unwrap());
+
+        let mut header_size = page.size();
+        loop {
+            assert_eq!(fr.next_page(&mut page).unwrap(), ReadStatus::Ok);
+            if page.granule_position() != 0 {
+                break;
+            }
+            header_size += page.size();
+        }
+        assert_eq!(
+            fishead.content_byte_offset(),
+            u64::try_from(header_size).unwrap()
+        );
+
+        let chains = fr.streams().unwrap().to_vec();
+        let index = chains[0].skeleton_index(1).unwrap();
+        assert_eq!(index.timestamp_denominator(), 25);
+        assert_eq!(
+            index.time(index.first_sample_time()),
+            Some(Duration::from_millis(40))
+        );
+        assert_eq!(
+            index.time(index.last_sample_time()),
+            Some(Duration::from_millis(4040))
+        );
+
+        // Only the first keyframes fit into the capacity.
+        let timestamps: Vec<i64> = index.keypoints().iter().map(|k| k.timestamp()).collect();
+        assert_eq!(timestamps, vec![1, 11, 21, 31, 41]);
+
+        // Each keypoint starts with the page of its keyframe.
+        let mapper = crate::SampleRateMapper::new(25).unwrap();
+        let mut packet = Packet::default();
+        for keypoint in index.keypoints() {
+            let time = index.time(keypoint.timestamp()).unwrap();
+            let result = fr.seek_to_time_with(1, time, &mapper).unwrap();
+            assert_eq!(result.byte_offset(), keypoint.byte_offset());
+
+            assert_eq!(fr.next_packet(&mut packet).unwrap(), ReadStatus::Ok);
+            assert_eq!(i64::from(packet.data()[0]), keypoint.timestamp());
+        }
+    }
+
+    #[test]
+    fn test_finish_open_stream() {
+        let mut sw = SkeletonWriter::new(Cursor::new(vec![]), 10, Fishead::new((0, 1), (0, 1)));
+        sw.begin_logical_stream(Fisbone::new(1, "video/x-test", (25, 1), 0), b"video")
+            .unwrap();
+        for frame in 1..=10_u64 {
+            sw.push_packet(1, &[0xAA; 100], frame).unwrap();
+        }
+
+        // The pending page of the logical bitstream is part of the chain.
+        let data = sw.finish().unwrap().into_inner();
+        let mut fr = FileReader::new(Cursor::new(data.clone()));
+        let mut page = Page::default();
+        assert_eq!(fr.next_page(&mut page).unwrap(), ReadStatus::Ok);
+        let fishead = Fishead::parse(page.data()).unwrap();
+        assert_eq!(fishead.segment_length(), u64::try_from(data.len()).unwrap());
+
+        let mut last_page = page.clone();
+        while fr.next_page(&mut page).unwrap() == ReadStatus::Ok {
+            last_page = page.clone();
+        }
+        assert_eq!(last_page.bitstream_serial_number(), 1);
+        assert_eq!(last_page.granule_position(), 10);
+        assert!(!last_page.is_eos());
+    }
+
+    #[test]
+    fn test_headers_finished() {
+        let mut sw = write_video(1);
+        assert!(matches!(
+            sw.push_header_packet(1, b"late"),
+            Err(WriteError::HeadersFinished)
+        ));
+        assert!(matches!(
+            sw.enable_index(2, 10),
+            Err(WriteError::HeadersFinished)
+        ));
+
+        // Without back-patching, the reserved index has no keypoints.
+        let data = sw.into_inner().into_inner();
+        let mut fr = FileReader::new(Cursor::new(data));
+        let chains = fr.streams().unwrap();
+        let index = chains[0].skeleton_index(1).unwrap();
+        assert_eq!(index.bitstream_serial_number(), 1);
+        assert!(index.keypoints().is_empty());
+    }
+}
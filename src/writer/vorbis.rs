@@ -0,0 +1,237 @@
+//! Vorbis logical bitstream writer.
+
+use std::io::Write;
+
+use super::StreamWriter;
+use crate::{VorbisComment, VorbisIdentification, VorbisSetup, WriteError};
+
+/// Writes a Vorbis logical bitstream.
+///
+/// The identification header is written alone on the BOS page. The comment and setup headers
+/// end their page, so that the first audio packet starts a new page. The granule positions
+/// are counted from the number of samples of each audio packet, which is either given or
+/// derived from the block sizes in the modes of the setup header.
+#[derive(Clone, Debug)]
+pub struct VorbisWriter<W: Write> {
+    inner: StreamWriter<W>,
+    bitstream_serial_number: u32,
+    setup: VorbisSetup,
+    granule_position: u64,
+    /// The block size of the previous audio packet.
+    previous_block_size: Option<u16>,
+}
+
+impl<W: Write> VorbisWriter<W> {
+    /// Creates a new `VorbisWriter` and writes the header pages of the logical bitstream.
+    ///
+    /// The header packets are validated before they are written.
+    pub fn new(
+        writer: W,
+        bitstream_serial_number: u32,
+        identification_packet: &[u8],
+        comment_packet: &[u8],
+        setup_packet: &[u8],
+    ) -> Result<Self, WriteError> {
+        let identification =
+            VorbisIdentification::parse(identification_packet).ok_or(WriteError::InvalidPacket)?;
+        VorbisComment::parse(comment_packet).ok_or(WriteError::InvalidPacket)?;
+        let setup =
+            VorbisSetup::parse(setup_packet, &identification).ok_or(WriteError::InvalidPacket)?;
+
+        let mut inner = StreamWriter::new(writer);
+        inner.begin_logical_stream(bitstream_serial_number, identification_packet)?;
+        inner.push_packet(bitstream_serial_number, comment_packet, 0)?;
+        inner.push_packet(bitstream_serial_number, setup_packet, 0)?;
+        inner.flush(bitstream_serial_number)?;
+
+        Ok(Self {
+            inner,
+            bitstream_serial_number,
+            setup,
+            granule_position: 0,
+            previous_block_size: None,
+        })
+    }
+
+    /// Returns the granule position after the audio packets written so far.
+    pub fn granule_position(&self) -> u64 {
+        self.granule_position
+    }
+
+    /// Queues an audio packet, which adds the given number of samples to the granule
+    /// position. The first audio packet only primes the decoder and adds no samples.
+    pub fn push_packet(&mut self, packet_data: &[u8], sample_count: u32) -> Result<(), WriteError> {
+        self.previous_block_size = self.setup.block_size(packet_data);
+        self.granule_position += u64::from(sample_count);
+        self.inner.push_packet(
+            self.bitstream_serial_number,
+            packet_data,
+            self.granule_position,
+        )
+    }
+
+    /// Queues an audio packet. The number of samples it adds to the granule position is
+    /// derived from its block size and the one of the previous audio packet.
+    ///
+    /// The first audio packet only primes the decoder and adds no samples.
+    pub fn push_audio_packet(&mut self, packet_data: &[u8]) -> Result<(), WriteError> {
+        let sample_count = self.next_sample_count(packet_data)?;
+        self.push_packet(packet_data, sample_count)
+    }
+
+    /// The current page is written and a new page is started.
+    pub fn flush(&mut self) -> Result<(), WriteError> {
+        self.inner.flush(self.bitstream_serial_number)
+    }
+
+    /// Ends the logical bitstream with the last audio packet and returns the writer.
+    ///
+    /// `end_trim` is the number of samples at the end of the last packet that are discarded
+    /// by the decoder. It must not exceed the samples the last packet adds.
+    pub fn finish(mut self, last_packet_data: &[u8], end_trim: u32) -> Result<W, WriteError> {
+        let sample_count = self.next_sample_count(last_packet_data)?;
+        if end_trim > sample_count {
+            return Err(WriteError::InvalidEndTrim);
+        }
+
+        self.granule_position += u64::from(sample_count - end_trim);
+        self.inner.end_logical_stream(
+            self.bitstream_serial_number,
+            last_packet_data,
+            self.granule_position,
+        )?;
+        Ok(self.inner.into_inner())
+    }
+
+    /// Returns the number of samples the audio packet adds after the previous audio packet.
+    fn next_sample_count(&self, packet_data: &[u8]) -> Result<u32, WriteError> {
+        let block_size = self
+            .setup
+            .block_size(packet_data)
+            .ok_or(WriteError::InvalidPacket)?;
+
+        Ok(self.previous_block_size.map_or(0, |previous_block_size| {
+            VorbisSetup::block_sample_count(previous_block_size, block_size)
+        }))
+    }
+}
+
+#[cfg(all(test, feature = "reader"))]
+mod tests {
+    #![allow(clippy::panic)]
+    #![allow(clippy::unwrap_used)]
+
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::test_util::{comment_packet, identification_packet, setup_packet};
+    use crate::{Page, ReadStatus, StreamReader};
+
+    fn read_pages(data: Vec<u8>) -> Vec<Page> {
+        let mut reader = StreamReader::new(Cursor::new(data));
+        let mut pages = Vec::new();
+        loop {
+            let mut page = Page::default();
+            match reader.next_page(&mut page).unwrap() {
+                ReadStatus::Ok => pages.push(page),
+                ReadStatus::Eof => return pages,
+                status => panic!("unexpected read status: {:?}", status),
+            }
+        }
+    }
+
+    fn writer() -> VorbisWriter<Vec<u8>> {
+        VorbisWriter::new(
+            Vec::new(),
+            7,
+            &identification_packet(),
+            &comment_packet(),
+            &setup_packet(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_vorbis_writer() {
+        let mut writer = writer();
+        // The first audio packet adds no samples, the next ones a quarter of both block sizes.
+        writer.push_audio_packet(&[0b00, 0xAA]).unwrap();
+        assert_eq!(writer.granule_position(), 0);
+        writer.push_audio_packet(&[0b10, 0xAA]).unwrap();
+        assert_eq!(writer.granule_position(), 576);
+        writer.flush().unwrap();
+        writer.push_audio_packet(&[0b10, 0xAA]).unwrap();
+        let data = writer.finish(&[0b00, 0xAA], 276).unwrap();
+
+        let pages = read_pages(data);
+        let headers: Vec<(u8, u64, usize)> = pages
+            .iter()
+            .map(|p| {
+                (
+                    p.header_type(),
+                    p.granule_position(),
+                    p.segment_table().len(),
+                )
+            })
+            .collect();
+
+        // The identification header is alone on the BOS page and the comment and setup
+        // headers end the second page.
+        assert_eq!(
+            headers,
+            [
+                (0x2, 0, 1),
+                (0, 0, 2),
+                (0, 576, 2),
+                (0, 1600, 1),
+                (0x4, 1900, 1)
+            ]
+        );
+        assert_eq!(pages[0].data(), identification_packet().as_slice());
+    }
+
+    #[test]
+    fn test_given_sample_counts() {
+        let mut writer = writer();
+        writer.push_packet(&[0b00, 0xAA], 0).unwrap();
+        writer.push_packet(&[0b10, 0xAA], 64 + 512).unwrap();
+        assert_eq!(writer.granule_position(), 576);
+
+        // The block sizes of the given packets are known to the derived sample counts.
+        writer.push_audio_packet(&[0b10, 0xAA]).unwrap();
+        assert_eq!(writer.granule_position(), 1600);
+    }
+
+    #[test]
+    fn test_invalid_headers() {
+        let result = VorbisWriter::new(
+            Vec::new(),
+            7,
+            &identification_packet(),
+            &setup_packet(),
+            &comment_packet(),
+        );
+        assert!(matches!(result, Err(WriteError::InvalidPacket)));
+    }
+
+    #[test]
+    fn test_invalid_audio_packets() {
+        let mut writer = writer();
+        // The packet type bit of a header packet.
+        assert!(matches!(
+            writer.push_audio_packet(&[0b01, 0xAA]),
+            Err(WriteError::InvalidPacket)
+        ));
+        assert!(matches!(
+            writer.push_audio_packet(&[]),
+            Err(WriteError::InvalidPacket)
+        ));
+        writer.push_audio_packet(&[0b00, 0xAA]).unwrap();
+
+        // The last packet adds 64 + 64 samples.
+        assert!(matches!(
+            writer.finish(&[0b00, 0xAA], 129),
+            Err(WriteError::InvalidEndTrim)
+        ));
+    }
+}